@@ -0,0 +1,376 @@
+//! Artifact service: serves the manifests and block digests of artifacts
+//! retained in the scratch directory to other builders, so divergent builds
+//! can be diagnosed without transferring the artifacts themselves.
+//!
+//! Messages are bincode-encoded and prefixed with their length as a
+//! big-endian `u32`. Each connection carries a single request and response.
+//!
+//! This is a plain TCP side channel, separate from the node's network, and
+//! connections aren't encrypted: anyone who can reach the service can learn
+//! what a builder built. Responses are signed with the builder's consensus
+//! key over the request and a random nonce chosen by the client, so they
+//! can't be forged or replayed by anyone on the path. They're still only
+//! suitable for diagnosing divergences and must never feed into a verdict.
+
+use crate::{
+    diff::BlockDigests,
+    error::{Error, ErrorKind},
+    manifest::Manifest,
+    prelude::*,
+    state::BuildId,
+    worker::{self, ARTIFACTS_DIR, MANIFEST_FILE},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use synchro::crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    hash::HashValue,
+    traits::{SigningKey, VerifyingKey},
+};
+
+/// Maximum size of a message (64 MiB)
+pub const MAX_MESSAGE_SIZE: u32 = 64 * 1024 * 1024;
+
+/// Timeout for connecting to, reading from, and writing to peers
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of connections served at once
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// Domain separator for the digests responses are signed over
+pub const SIGNATURE_DOMAIN: &[u8] = b"synchronicity/artifact_service/response";
+
+/// Requests to the artifact service
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    /// Get the manifest of a retained build
+    Manifest(BuildId),
+
+    /// Get the block digests of an artifact of a retained build
+    BlockDigests(BuildId, String),
+}
+
+/// Responses from the artifact service
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    /// Manifest of a retained build
+    Manifest(Manifest),
+
+    /// Block digests of an artifact
+    BlockDigests(BlockDigests),
+
+    /// The build (or artifact) isn't retained by this builder
+    NotFound,
+
+    /// The request couldn't be processed
+    Error(String),
+}
+
+/// Request along with the nonce its response's signature must cover
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct SignedRequest {
+    /// Random nonce chosen by the client
+    nonce: [u8; 32],
+
+    /// Request being made
+    request: Request,
+}
+
+/// Response signed by the builder serving it
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct SignedResponse {
+    /// Response to the request
+    response: Response,
+
+    /// Signature over the request and response (see `signing_hash`)
+    signature: Ed25519Signature,
+}
+
+/// Serves retained artifacts from the scratch directory
+#[derive(Clone)]
+pub struct ArtifactServer {
+    /// Scratch directory builds are retained in
+    scratch_dir: PathBuf,
+
+    /// Builder's consensus key, which responses are signed with
+    private_key: Arc<Ed25519PrivateKey>,
+}
+
+impl ArtifactServer {
+    /// Create a server for the builds retained in the given scratch
+    /// directory, signing responses with the given consensus key
+    pub fn new(scratch_dir: impl Into<PathBuf>, private_key: Ed25519PrivateKey) -> Self {
+        Self {
+            scratch_dir: scratch_dir.into(),
+            private_key: Arc::new(private_key),
+        }
+    }
+
+    /// Bind to the given address and serve requests in a background thread
+    pub fn spawn(self, addr: SocketAddr) -> Result<SocketAddr, Error> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| ErrorKind::Io.context(format!("couldn't bind {}: {}", addr, e)))?;
+
+        let local_addr = listener.local_addr()?;
+        thread::spawn(move || self.serve(listener));
+        Ok(local_addr)
+    }
+
+    /// Serve requests from the given listener forever, each connection in
+    /// its own thread
+    pub fn serve(&self, listener: TcpListener) {
+        let active = Arc::new(AtomicUsize::new(0));
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    status_warn!("artifact service error: {}", e);
+                    continue;
+                }
+            };
+
+            // Connections beyond the limit are dropped rather than queued
+            if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                active.fetch_sub(1, Ordering::SeqCst);
+                status_warn!("artifact service: too many connections, dropping one");
+                continue;
+            }
+
+            let server = self.clone();
+            let active = Arc::clone(&active);
+
+            thread::spawn(move || {
+                if let Err(e) = server.handle(stream) {
+                    status_warn!("artifact service error: {}", e);
+                }
+
+                active.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    /// Respond to a single request
+    pub fn respond(&self, request: &Request) -> Response {
+        let result = match request {
+            Request::Manifest(build_id) => self.manifest(build_id).map(|manifest| {
+                manifest
+                    .map(Response::Manifest)
+                    .unwrap_or(Response::NotFound)
+            }),
+            Request::BlockDigests(build_id, path) => {
+                self.block_digests(build_id, path).map(|digests| {
+                    digests
+                        .map(Response::BlockDigests)
+                        .unwrap_or(Response::NotFound)
+                })
+            }
+        };
+
+        result.unwrap_or_else(|e| Response::Error(e.to_string()))
+    }
+
+    /// Handle a connection
+    fn handle(&self, mut stream: TcpStream) -> Result<(), Error> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let signed_request: SignedRequest = read_message(&mut stream)?;
+        let response = self.respond(&signed_request.request);
+        let signature = self
+            .private_key
+            .sign_message(&signing_hash(&signed_request, &response)?);
+
+        write_message(
+            &mut stream,
+            &SignedResponse {
+                response,
+                signature,
+            },
+        )
+    }
+
+    /// Load the manifest the worker saved for a retained build (if any)
+    fn manifest(&self, build_id: &BuildId) -> Result<Option<Manifest>, Error> {
+        let manifest_path = worker::build_dir(&self.scratch_dir, build_id).join(MANIFEST_FILE);
+
+        let encoded = match fs::read_to_string(&manifest_path) {
+            Ok(encoded) => encoded,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => fail!(
+                ErrorKind::Io,
+                "couldn't read {}: {}",
+                manifest_path.display(),
+                e
+            ),
+        };
+
+        encoded.parse().map(Some)
+    }
+
+    /// Compute the block digests of an artifact of a retained build.
+    ///
+    /// Only paths listed in the build's manifest are served.
+    fn block_digests(&self, build_id: &BuildId, path: &str) -> Result<Option<BlockDigests>, Error> {
+        let manifest = match self.manifest(build_id)? {
+            Some(manifest) => manifest,
+            None => return Ok(None),
+        };
+
+        let artifacts_dir = worker::build_dir(&self.scratch_dir, build_id).join(ARTIFACTS_DIR);
+
+        match manifest.entries.iter().find(|entry| entry.path == path) {
+            Some(entry) => BlockDigests::from_file(&artifacts_dir.join(&entry.path)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Client for another builder's artifact service
+pub struct ArtifactClient {
+    /// Address of the artifact service
+    addr: SocketAddr,
+
+    /// Builder's consensus public key, which responses must be signed with
+    public_key: Ed25519PublicKey,
+}
+
+impl ArtifactClient {
+    /// Create a client for the artifact service at the given address, run
+    /// by the builder with the given consensus public key
+    pub fn new(addr: SocketAddr, public_key: Ed25519PublicKey) -> Self {
+        Self { addr, public_key }
+    }
+
+    /// Get the manifest of a build, if the builder retains it
+    pub fn manifest(&self, build_id: &BuildId) -> Result<Option<Manifest>, Error> {
+        match self.request(&Request::Manifest(*build_id))? {
+            Response::Manifest(manifest) => {
+                manifest.validate()?;
+                Ok(Some(manifest))
+            }
+            Response::NotFound => Ok(None),
+            response => unexpected_response(response),
+        }
+    }
+
+    /// Get the block digests of an artifact, if the builder retains it
+    pub fn block_digests(
+        &self,
+        build_id: &BuildId,
+        path: &str,
+    ) -> Result<Option<BlockDigests>, Error> {
+        match self.request(&Request::BlockDigests(*build_id, path.to_owned()))? {
+            Response::BlockDigests(digests) => {
+                digests.validate()?;
+                Ok(Some(digests))
+            }
+            Response::NotFound => Ok(None),
+            response => unexpected_response(response),
+        }
+    }
+
+    /// Send a request and wait for the response, checking it's signed by
+    /// the builder
+    fn request(&self, request: &Request) -> Result<Response, Error> {
+        let mut stream = TcpStream::connect_timeout(&self.addr, TIMEOUT).map_err(|e| {
+            ErrorKind::Client.context(format!("couldn't connect to {}: {}", self.addr, e))
+        })?;
+
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let signed_request = SignedRequest {
+            nonce: rand::random(),
+            request: request.clone(),
+        };
+
+        write_message(&mut stream, &signed_request)?;
+
+        let SignedResponse {
+            response,
+            signature,
+        } = read_message(&mut stream)?;
+
+        self.public_key
+            .verify_signature(&signing_hash(&signed_request, &response)?, &signature)
+            .map_err(|e| {
+                ErrorKind::Client.context(format!(
+                    "bad signature on response from {}: {}",
+                    self.addr, e
+                ))
+            })?;
+
+        Ok(response)
+    }
+}
+
+/// Compute the digest a response is signed over, which also covers the
+/// request (and its nonce) so responses can't be replayed
+fn signing_hash(signed_request: &SignedRequest, response: &Response) -> Result<HashValue, Error> {
+    let request_bytes =
+        bincode::serialize(signed_request).map_err(|e| ErrorKind::Client.context(e))?;
+    let response_bytes = bincode::serialize(response).map_err(|e| ErrorKind::Client.context(e))?;
+
+    let mut hasher = Sha256::new();
+    hasher.input(SIGNATURE_DOMAIN);
+    hasher.input(&request_bytes);
+    hasher.input(&response_bytes);
+
+    let mut digest = [0u8; HashValue::LENGTH];
+    digest.copy_from_slice(&hasher.result());
+    Ok(HashValue::new(digest))
+}
+
+/// Turn an unexpected response into an error
+fn unexpected_response<T>(response: Response) -> Result<T, Error> {
+    match response {
+        Response::Error(msg) => fail!(ErrorKind::Client, "artifact service error: {}", msg),
+        other => fail!(ErrorKind::Client, "unexpected response: {:?}", other),
+    }
+}
+
+/// Read a length-prefixed message
+fn read_message<T: DeserializeOwned>(stream: &mut TcpStream) -> Result<T, Error> {
+    let mut len_bytes = [0u8; 4];
+    stream.read_exact(&mut len_bytes)?;
+
+    let len = u32::from_be_bytes(len_bytes);
+
+    if len > MAX_MESSAGE_SIZE {
+        fail!(ErrorKind::Client, "message too large: {} bytes", len);
+    }
+
+    let mut bytes = vec![0u8; len as usize];
+    stream.read_exact(&mut bytes)?;
+    bincode::deserialize(&bytes).map_err(|e| ErrorKind::Client.context(e).into())
+}
+
+/// Write a length-prefixed message
+fn write_message<T: Serialize>(stream: &mut TcpStream, message: &T) -> Result<(), Error> {
+    let bytes = bincode::serialize(message).map_err(|e| ErrorKind::Client.context(e))?;
+
+    if bytes.len() > MAX_MESSAGE_SIZE as usize {
+        fail!(
+            ErrorKind::Client,
+            "message too large: {} bytes",
+            bytes.len()
+        );
+    }
+
+    stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    stream.write_all(&bytes)?;
+    Ok(())
+}
@@ -0,0 +1,171 @@
+//! Build backends: fetch and build a crate, isolated (to varying degrees)
+//! from the host.
+//!
+//! The consensus side of Synchronicity only ever talks to a `BuildBackend`,
+//! so nodes (and tests) can run without Docker.
+
+mod docker;
+mod local;
+
+pub use self::{docker::DockerBackend, local::LocalBackend};
+
+use crate::{
+    error::{Error, ErrorKind},
+    payload::{BuildEnv, BuildRequest, Profile},
+    prelude::*,
+    registry::Registry,
+};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+use tar::Archive;
+
+/// Build backends
+pub trait BuildBackend: Send + Sync {
+    /// Check this backend can honor the given build environment exactly,
+    /// returning an error explaining why not if it can't
+    fn check_env(&self, env: &BuildEnv) -> Result<(), Error>;
+
+    /// Get the registry to fetch the requested crate from (by default, the
+    /// one named in the request)
+    fn registry(&self, request: &BuildRequest) -> Result<Registry, Error> {
+        request.registry()
+    }
+
+    /// Build the requested crate from its (verified) `.crate` tarball,
+    /// placing the build outputs (i.e. the top-level files of
+    /// `target/<triple>/release`) into a directory within `build_dir` and
+    /// returning its path
+    fn build(
+        &self,
+        request: &BuildRequest,
+        crate_file: &Path,
+        build_dir: &Path,
+    ) -> Result<PathBuf, Error>;
+}
+
+/// Subdirectory of a build directory where backends place build outputs
+pub const OUTPUT_DIR: &str = "output";
+
+/// Name of the lockfile a build environment can pin
+pub const LOCKFILE: &str = "Cargo.lock";
+
+/// Unpack a `.crate` tarball into the given directory, returning the path to
+/// the crate's sources
+pub(crate) fn unpack_crate(
+    request: &BuildRequest,
+    crate_path: &Path,
+    dir: &Path,
+) -> Result<PathBuf, Error> {
+    File::open(crate_path)
+        .and_then(|file| Archive::new(GzDecoder::new(file)).unpack(dir))
+        .map_err(|e| {
+            ErrorKind::Build.context(format!("couldn't unpack {}: {}", crate_path.display(), e))
+        })?;
+
+    // `.crate` files unpack into a `<name>-<version>` directory
+    let source_dir = dir.join(format!("{}-{}", request.crate_name, request.version));
+    check_lockfile(&request.env, &source_dir)?;
+    Ok(source_dir)
+}
+
+/// Check the crate's `Cargo.lock` is the one the build environment pins (if
+/// any), before anything gets a chance to generate or update it
+pub(crate) fn check_lockfile(env: &BuildEnv, source_dir: &Path) -> Result<(), Error> {
+    let expected = match &env.lockfile {
+        Some(digest) => digest,
+        None => return Ok(()),
+    };
+
+    let lockfile = match fs::read(source_dir.join(LOCKFILE)) {
+        Ok(lockfile) => lockfile,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            fail!(ErrorKind::Build, "crate doesn't ship a {}", LOCKFILE)
+        }
+        Err(e) => fail!(ErrorKind::Io, "couldn't read {}: {}", LOCKFILE, e),
+    };
+
+    if Sha256::digest(&lockfile).as_slice() != expected {
+        fail!(
+            ErrorKind::Build,
+            "{} doesn't match the pinned digest {}",
+            LOCKFILE,
+            hex::encode(expected)
+        );
+    }
+
+    Ok(())
+}
+
+/// File name of the `.crate` tarball for a build request
+pub(crate) fn crate_file_name(request: &BuildRequest) -> String {
+    format!("{}-{}.crate", request.crate_name, request.version)
+}
+
+/// Copy the top-level files in a target directory (but not subdirectories
+/// like `deps` or `build`) into an output directory
+pub(crate) fn copy_outputs(target_dir: &Path, output_dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(output_dir)?;
+
+    for entry in fs::read_dir(target_dir)? {
+        let entry = entry?;
+
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), output_dir.join(entry.file_name()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Arguments to `cargo` for building in the given environment
+pub(crate) fn cargo_build_args(env: &BuildEnv) -> Vec<String> {
+    let mut args = vec![
+        "build".to_owned(),
+        "--target".to_owned(),
+        env.target.clone(),
+    ];
+
+    if env.profile == Profile::Release {
+        args.push("--release".to_owned());
+    }
+
+    if env.no_default_features {
+        args.push("--no-default-features".to_owned());
+    }
+
+    if !env.features.is_empty() {
+        let features: Vec<_> = env.features.iter().map(String::as_str).collect();
+        args.push("--features".to_owned());
+        args.push(features.join(","));
+    }
+
+    if env.lockfile.is_some() {
+        args.push("--locked".to_owned());
+    }
+
+    args
+}
+
+/// Environment variables for building in the given environment, where the
+/// sources and target directory are located under `build_dir`
+pub(crate) fn cargo_build_vars(env: &BuildEnv, build_dir: &Path) -> Vec<(&'static str, String)> {
+    let mut rustflags = env.rustflags.clone();
+
+    if let Some(prefix) = &env.remap_path_prefix {
+        rustflags.push(format!(
+            "--remap-path-prefix={}={}",
+            build_dir.display(),
+            prefix
+        ));
+    }
+
+    vec![
+        ("RUSTFLAGS", rustflags.join(" ")),
+        ("SOURCE_DATE_EPOCH", env.source_date_epoch.to_string()),
+    ]
+}
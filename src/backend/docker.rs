@@ -0,0 +1,129 @@
+//! Rustwide-managed Docker build backend
+
+use super::{
+    cargo_build_args, cargo_build_vars, copy_outputs, unpack_crate, BuildBackend, OUTPUT_DIR,
+};
+use crate::{
+    error::{Error, ErrorKind},
+    payload::{BuildEnv, BuildRequest},
+};
+use rustwide::{
+    cmd::{Command, SandboxBuilder},
+    Crate, Toolchain, Workspace, WorkspaceBuilder,
+};
+use std::path::{Path, PathBuf};
+
+/// Subdirectory of the scratch directory containing the Rustwide workspace
+pub const WORKSPACE_DIR: &str = "rustwide";
+
+/// User agent Rustwide uses when fetching crates and toolchains
+pub const USER_AGENT: &str = concat!("synchronicity/", env!("CARGO_PKG_VERSION"));
+
+/// Directory inside of build containers containing the sources and target
+/// directory, which is remapped to the build environment's path prefix
+pub const CONTAINER_ROOT: &str = "/opt/rustwide";
+
+/// Subdirectory of a build directory `.crate` tarballs are unpacked into
+pub const SOURCE_DIR: &str = "source";
+
+/// Memory limit for builds (bytes)
+pub const MEMORY_LIMIT: usize = 4 * 1024 * 1024 * 1024;
+
+/// Builds crates inside of Docker containers managed by Rustwide
+pub struct DockerBackend {
+    /// Rustwide workspace
+    workspace: Workspace,
+}
+
+impl DockerBackend {
+    /// Initialize a Rustwide workspace inside of the given scratch directory
+    pub fn init(scratch_dir: &Path) -> Result<Self, Error> {
+        let workspace = WorkspaceBuilder::new(&scratch_dir.join(WORKSPACE_DIR), USER_AGENT)
+            .init()
+            .map_err(|e| ErrorKind::Build.context(e.to_string()))?;
+
+        Ok(Self { workspace })
+    }
+
+    /// Get the Rustwide toolchain for a build environment
+    fn toolchain(&self, env: &BuildEnv) -> Toolchain {
+        Toolchain::Dist {
+            name: env.toolchain.rustup_name().into(),
+        }
+    }
+}
+
+impl BuildBackend for DockerBackend {
+    /// Any toolchain `rustup` can install can be honored, so long as the
+    /// installed `rustc` matches the requested commit hash
+    fn check_env(&self, env: &BuildEnv) -> Result<(), Error> {
+        let toolchain = self.toolchain(env);
+
+        toolchain
+            .install(&self.workspace)
+            .and_then(|_| toolchain.add_target(&self.workspace, &env.target))
+            .map_err(|e| ErrorKind::Build.context(e.to_string()))?;
+
+        let version_info = Command::new(&self.workspace, toolchain.rustc())
+            .args(&["-vV"])
+            .log_output(false)
+            .run_capture()
+            .map_err(|e| ErrorKind::Build.context(e.to_string()))?
+            .stdout_lines()
+            .join("\n");
+
+        env.toolchain.check_version_info(&version_info)
+    }
+
+    fn build(
+        &self,
+        request: &BuildRequest,
+        crate_file: &Path,
+        build_dir: &Path,
+    ) -> Result<PathBuf, Error> {
+        let env = &request.env;
+        let toolchain = self.toolchain(env);
+
+        // Build the verified tarball rather than letting Rustwide fetch the
+        // crate again, since it doesn't check sources against the index
+        let source_dir = unpack_crate(request, crate_file, &build_dir.join(SOURCE_DIR))?;
+        let krate = Crate::local(&source_dir);
+
+        krate
+            .fetch(&self.workspace)
+            .map_err(|e| ErrorKind::Build.context(e.to_string()))?;
+
+        // Builds have no network access: dependencies are fetched beforehand
+        let sandbox = SandboxBuilder::new()
+            .memory_limit(Some(MEMORY_LIMIT))
+            .enable_networking(false);
+
+        let mut rustwide_build_dir = self.workspace.build_dir(&request.id().to_string());
+        let output_dir = build_dir.join(OUTPUT_DIR);
+
+        rustwide_build_dir
+            .build(&toolchain, &krate, sandbox)
+            .run(|build| {
+                let mut cargo = build.cargo().args(&cargo_build_args(env));
+
+                for (key, value) in cargo_build_vars(env, Path::new(CONTAINER_ROOT)) {
+                    cargo = cargo.env(key, value);
+                }
+
+                cargo.run()?;
+
+                // Copy outputs out of Rustwide's build directory, which is
+                // reused between builds
+                let target_dir = build
+                    .host_target_dir()
+                    .join(&env.target)
+                    .join(env.profile.target_subdir());
+
+                copy_outputs(&target_dir, &output_dir)?;
+                Ok(())
+            })
+            .map_err(|e| ErrorKind::Build.context(e.to_string()))?;
+
+        Ok(output_dir)
+    }
+}
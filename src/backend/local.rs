@@ -0,0 +1,151 @@
+//! Local build backend: builds crates with the host's `cargo` in a
+//! temporary directory, fetching crates from local mirrors.
+//!
+//! This provides no isolation whatsoever, but requires neither Docker nor
+//! network access, which makes it useful for testing.
+
+use super::{
+    cargo_build_args, cargo_build_vars, copy_outputs, unpack_crate, BuildBackend, OUTPUT_DIR,
+};
+use crate::{
+    error::{Error, ErrorKind},
+    payload::{BuildEnv, BuildRequest},
+    prelude::*,
+    registry::Registry,
+};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempfile::TempDir;
+
+/// Builds crates using the host's `cargo`
+pub struct LocalBackend {
+    /// Index directory of a local crates.io mirror (whose `config.json`
+    /// points at local `.crate` files)
+    mirror_dir: PathBuf,
+
+    /// Index directories of local mirrors of alternative registries, by the
+    /// index URL build requests name them with
+    registry_mirrors: BTreeMap<String, PathBuf>,
+}
+
+impl LocalBackend {
+    /// Create a local backend which fetches crates.io crates from the
+    /// mirror in the given index directory
+    pub fn new(mirror_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            mirror_dir: mirror_dir.into(),
+            registry_mirrors: BTreeMap::new(),
+        }
+    }
+
+    /// Fetch crates from the registry with the given index URL from the
+    /// mirror in the given index directory instead
+    pub fn with_registry_mirror(
+        mut self,
+        index_url: impl Into<String>,
+        mirror_dir: impl Into<PathBuf>,
+    ) -> Self {
+        self.registry_mirrors
+            .insert(index_url.into(), mirror_dir.into());
+        self
+    }
+}
+
+impl BuildBackend for LocalBackend {
+    /// The host's toolchain is used as-is, so it must be pinned by commit
+    /// hash (a date can't be verified) and have the target installed
+    fn check_env(&self, env: &BuildEnv) -> Result<(), Error> {
+        if env.toolchain.commit_hash.is_none() {
+            fail!(
+                ErrorKind::Build,
+                "local backend requires toolchains pinned by commit hash"
+            );
+        }
+
+        env.toolchain.check_version_info(&rustc(&["-vV"])?)?;
+
+        let sysroot = PathBuf::from(rustc(&["--print", "sysroot"])?.trim());
+        let target_libdir = sysroot.join("lib/rustlib").join(&env.target).join("lib");
+
+        if !target_libdir.is_dir() {
+            fail!(
+                ErrorKind::Build,
+                "target {} isn't installed for the host toolchain",
+                env.target
+            );
+        }
+
+        Ok(())
+    }
+
+    /// crates.io (and any mirrored registry) is replaced by its local mirror
+    fn registry(&self, request: &BuildRequest) -> Result<Registry, Error> {
+        let registry = request.registry()?;
+
+        let mirror_dir = match &request.registry {
+            None => &self.mirror_dir,
+            Some(index_url) => match self.registry_mirrors.get(index_url) {
+                Some(mirror_dir) => mirror_dir,
+                None => return Ok(registry),
+            },
+        };
+
+        Ok(Registry::File(mirror_dir.clone()))
+    }
+
+    fn build(
+        &self,
+        request: &BuildRequest,
+        crate_file: &Path,
+        build_dir: &Path,
+    ) -> Result<PathBuf, Error> {
+        let env = &request.env;
+        let temp_dir = TempDir::new().map_err(|e| ErrorKind::Io.context(e))?;
+        let source_dir = unpack_crate(request, crate_file, temp_dir.path())?;
+
+        let target_dir = temp_dir.path().join("target");
+
+        let status = Command::new("cargo")
+            .args(cargo_build_args(env))
+            .arg("--offline")
+            .current_dir(&source_dir)
+            .env("CARGO_TARGET_DIR", &target_dir)
+            .envs(cargo_build_vars(env, temp_dir.path()))
+            .status()
+            .map_err(|e| ErrorKind::Io.context(e))?;
+
+        if !status.success() {
+            fail!(ErrorKind::Build, "cargo build failed: {}", status);
+        }
+
+        let output_dir = build_dir.join(OUTPUT_DIR);
+        let profile_dir = target_dir
+            .join(&env.target)
+            .join(env.profile.target_subdir());
+
+        copy_outputs(&profile_dir, &output_dir).map_err(|e| ErrorKind::Io.context(e))?;
+        Ok(output_dir)
+    }
+}
+
+/// Run the host's `rustc` with the given arguments, returning its output
+fn rustc(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("rustc")
+        .args(args)
+        .output()
+        .map_err(|e| ErrorKind::Io.context(e))?;
+
+    if !output.status.success() {
+        fail!(
+            ErrorKind::Build,
+            "rustc {} failed: {}",
+            args.join(" "),
+            output.status
+        );
+    }
+
+    String::from_utf8(output.stdout).map_err(|e| ErrorKind::Build.context(e).into())
+}
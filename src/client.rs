@@ -0,0 +1,310 @@
+//! Client for submitting transactions to a local Synchronicity node
+
+use crate::{
+    config::SynchronicityConfig,
+    error::{Error, ErrorKind},
+    participant::Ledger,
+    payload::{BuildRequest, Payload},
+    prelude::*,
+    state::{self, Build, BuildId, Params},
+    verdict::Verdict,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use synchro::{
+    crypto::ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+    mempool::proto::{
+        mempool::{AddTransactionWithValidationRequest, MempoolClient},
+        mempool_status::MempoolAddTransactionStatusCode,
+    },
+    storage_client::{StorageRead, StorageReadServiceClient},
+    types::{
+        access_path::AccessPath,
+        account_address::AccountAddress,
+        get_with_proof::{RequestItem, ResponseItem},
+        transaction::RawTransaction,
+    },
+};
+
+/// Maximum amount of gas for submitted transactions (gas is unused by the
+/// Synchronicity executor, but mempool requires a value)
+pub const MAX_GAS_AMOUNT: u64 = 1_000_000;
+
+/// How long submitted transactions remain valid
+pub const TRANSACTION_EXPIRATION: Duration = Duration::from_secs(60);
+
+/// How many successive sequence numbers to try when mempool already holds a
+/// transaction with the one we picked (e.g. one submitted by another process
+/// signing with the same key)
+pub const MAX_SEQUENCE_NUMBER_ATTEMPTS: u64 = 16;
+
+/// Tracks the latest transaction submitted from an account, so transactions
+/// submitted before earlier ones have executed don't reuse their sequence
+/// numbers
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    /// Sequence number and expiration time of the latest submission
+    latest: Option<(u64, Duration)>,
+}
+
+impl SequenceTracker {
+    /// Next sequence number to submit with, given the account's sequence
+    /// number in the latest ledger state and the current time (since the
+    /// UNIX epoch).
+    ///
+    /// Once the latest submission has expired every earlier one has too, so
+    /// any which never executed won't fill the gap before it: counting then
+    /// starts over from the ledger's sequence number.
+    pub fn next(&self, committed: u64, now: Duration) -> u64 {
+        match self.latest {
+            Some((sequence_number, expiration_time)) if now < expiration_time => {
+                committed.max(sequence_number + 1)
+            }
+            _ => committed,
+        }
+    }
+
+    /// Record a transaction which was accepted into mempool
+    pub fn submitted(&mut self, sequence_number: u64, expiration_time: Duration) {
+        self.latest = Some((sequence_number, expiration_time));
+    }
+}
+
+/// Client which signs transactions with the node's consensus key and submits
+/// them to the node's mempool.
+///
+/// Everything in a process which submits from the node's account should do
+/// so through the same client (e.g. by sharing it in an `Arc`), so sequence
+/// numbers are handed out in order.
+pub struct Client {
+    /// Mempool gRPC client
+    mempool: MempoolClient,
+
+    /// Storage gRPC client
+    storage: StorageReadServiceClient,
+
+    /// Account transactions are sent from (derived from the consensus key)
+    sender: AccountAddress,
+
+    /// Key used to sign transactions
+    private_key: Ed25519PrivateKey,
+
+    /// Public key corresponding to `private_key`
+    public_key: Ed25519PublicKey,
+
+    /// Latest transaction submitted through this client
+    sequence_tracker: Mutex<SequenceTracker>,
+}
+
+impl Client {
+    /// Create a new client for the node described by the given config
+    pub fn new(config: &SynchronicityConfig) -> Result<Self, Error> {
+        let private_key = config.load_consensus_key()?;
+        let public_key = Ed25519PublicKey::from(&private_key);
+
+        // The executor only accepts transactions from the account derived
+        // from the key they're signed with
+        let sender = AccountAddress::from_public_key(&public_key);
+
+        Ok(Self {
+            mempool: config.mempool_client(),
+            storage: config.storage_read_client(),
+            sender,
+            private_key,
+            public_key,
+            sequence_tracker: Mutex::new(SequenceTracker::default()),
+        })
+    }
+
+    /// Get the account transactions are sent from
+    pub fn sender(&self) -> AccountAddress {
+        self.sender
+    }
+
+    /// Get the next sequence number for the sender's account
+    pub fn sequence_number(&self) -> Result<u64, Error> {
+        let path = state::sequence_number_path(self.sender);
+        Ok(self.get(&path)?.unwrap_or(0))
+    }
+
+    /// Get the timestamp of the latest block (microseconds)
+    pub fn now(&self) -> Result<u64, Error> {
+        Ok(self.get(&state::timestamp_path())?.unwrap_or(0))
+    }
+
+    /// Get the group's protocol parameters
+    pub fn params(&self) -> Result<Params, Error> {
+        Ok(self.get(&state::params_path())?.unwrap_or_default())
+    }
+
+    /// Get the builds which have not yet reached a verdict
+    pub fn pending_builds(&self) -> Result<BTreeSet<BuildId>, Error> {
+        Ok(self.get(&state::pending_builds_path())?.unwrap_or_default())
+    }
+
+    /// Get the record of a build (if it has been requested)
+    pub fn build(&self, build_id: &BuildId) -> Result<Option<Build>, Error> {
+        self.get(&state::build_path(build_id))
+    }
+
+    /// Get the verdict on a build request (if one has been reached)
+    pub fn verdict(&self, request: &BuildRequest) -> Result<Option<Verdict>, Error> {
+        let verdict: Option<Verdict> = self.get(&state::request_verdict_path(request))?;
+
+        // The verdict path holds the latest verdict for any toolchain
+        Ok(verdict.filter(|verdict| verdict.build_id == request.id()))
+    }
+
+    /// Sign a transaction containing the given payload and submit it to
+    /// mempool, returning its sequence number
+    pub fn submit(&self, payload: &Payload) -> Result<u64, Error> {
+        // Held until mempool has accepted the transaction, so concurrent
+        // submissions through this client get consecutive sequence numbers
+        let mut sequence_tracker = self
+            .sequence_tracker
+            .lock()
+            .expect("sequence tracker lock poisoned");
+
+        let committed = self.sequence_number()?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock is before the UNIX epoch");
+
+        let expiration_time = now + TRANSACTION_EXPIRATION;
+        let mut sequence_number = sequence_tracker.next(committed, now);
+
+        for _ in 0..MAX_SEQUENCE_NUMBER_ATTEMPTS {
+            if self.add_transaction(payload, sequence_number, committed, expiration_time)? {
+                sequence_tracker.submitted(sequence_number, expiration_time);
+                return Ok(sequence_number);
+            }
+
+            sequence_number += 1;
+        }
+
+        fail!(
+            ErrorKind::Client,
+            "mempool already holds transactions with sequence numbers {}..{}",
+            sequence_number - MAX_SEQUENCE_NUMBER_ATTEMPTS,
+            sequence_number
+        )
+    }
+
+    /// Sign a transaction with the given sequence number and add it to
+    /// mempool, returning `false` if mempool already holds a transaction
+    /// from this account with that sequence number
+    fn add_transaction(
+        &self,
+        payload: &Payload,
+        sequence_number: u64,
+        committed: u64,
+        expiration_time: Duration,
+    ) -> Result<bool, Error> {
+        let signed_txn = RawTransaction::new(
+            self.sender,
+            sequence_number,
+            payload.to_transaction_payload(),
+            MAX_GAS_AMOUNT,
+            0,
+            expiration_time,
+        )
+        .sign(&self.private_key, self.public_key.clone())
+        .map_err(|e| ErrorKind::Client.context(e.to_string()))?
+        .into_inner();
+
+        let mut request = AddTransactionWithValidationRequest::default();
+        request.signed_txn = Some(signed_txn.into());
+        request.max_gas_cost = MAX_GAS_AMOUNT;
+
+        // Mempool drops transactions from the account below this number, so
+        // it must be the ledger's, not the one we're submitting with
+        request.latest_sequence_number = committed;
+
+        let response = self
+            .mempool
+            .add_transaction_with_validation(&request)
+            .map_err(|e| ErrorKind::Client.context(e))?;
+
+        match response.status {
+            Some(status) if status.code == MempoolAddTransactionStatusCode::Valid as i32 => {
+                Ok(true)
+            }
+            Some(status)
+                if status.code == MempoolAddTransactionStatusCode::InvalidUpdate as i32 =>
+            {
+                Ok(false)
+            }
+            Some(status) => fail!(
+                ErrorKind::Client,
+                "mempool rejected transaction: {} (code {})",
+                status.message,
+                status.code
+            ),
+            None => fail!(ErrorKind::Client, "no status in mempool response"),
+        }
+    }
+
+    /// Get a value from the latest ledger state
+    fn get<T: DeserializeOwned>(&self, path: &AccessPath) -> Result<Option<T>, Error> {
+        let request = RequestItem::GetAccountState {
+            address: path.address,
+        };
+
+        let (mut response_items, ..) = self
+            .storage
+            .update_to_latest_ledger(0, vec![request])
+            .map_err(|e| ErrorKind::Client.context(e.to_string()))?;
+
+        let blob = match response_items.pop() {
+            Some(ResponseItem::GetAccountState {
+                account_state_with_proof,
+            }) => match account_state_with_proof.blob {
+                Some(blob) => blob,
+                None => return Ok(None),
+            },
+            _ => fail!(ErrorKind::Client, "unexpected response from storage"),
+        };
+
+        let values = BTreeMap::<Vec<u8>, Vec<u8>>::try_from(&blob)
+            .map_err(|e| ErrorKind::Client.context(e.to_string()))?;
+
+        match values.get(&path.path) {
+            Some(bytes) => Ok(Some(
+                bincode::deserialize(bytes).map_err(|e| ErrorKind::Client.context(e))?,
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Ledger for Client {
+    fn sender(&self) -> AccountAddress {
+        Client::sender(self)
+    }
+
+    fn now(&self) -> Result<u64, Error> {
+        Client::now(self)
+    }
+
+    fn params(&self) -> Result<Params, Error> {
+        Client::params(self)
+    }
+
+    fn pending_builds(&self) -> Result<BTreeSet<BuildId>, Error> {
+        Client::pending_builds(self)
+    }
+
+    fn build(&self, build_id: &BuildId) -> Result<Option<Build>, Error> {
+        Client::build(self, build_id)
+    }
+
+    fn submit(&self, payload: &Payload) -> Result<u64, Error> {
+        Client::submit(self, payload)
+    }
+}
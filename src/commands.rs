@@ -1,10 +1,18 @@
 //! Synchronicity Subcommands
 
+mod diff;
+mod genesis;
 mod init;
+mod log;
 mod start;
+mod submit;
+mod verify;
 mod version;
 
-use self::{init::InitCmd, start::StartCmd, version::VersionCmd};
+use self::{
+    diff::DiffCmd, genesis::GenesisCmd, init::InitCmd, log::LogCmd, start::StartCmd,
+    submit::SubmitCmd, verify::VerifyCmd, version::VersionCmd,
+};
 use crate::config::{SynchronicityConfig, CONFIG_FILE};
 use abscissa_core::{Command, Configurable, Help, Options, Runnable};
 use std::path::PathBuf;
@@ -24,9 +32,29 @@ pub enum SynchronicityCmd {
     #[options(help = "initialize application home/config")]
     Init(InitCmd),
 
+    /// The `genesis` subcommand
+    #[options(help = "configure nodes as a group from their peer_info.toml files")]
+    Genesis(GenesisCmd),
+
     /// The `start` subcommand
     #[options(help = "start the application")]
     Start(StartCmd),
+
+    /// The `log` subcommand
+    #[options(help = "inspect the reproducibility log")]
+    Log(LogCmd),
+
+    /// The `submit` subcommand
+    #[options(help = "request that the group build a crate version")]
+    Submit(SubmitCmd),
+
+    /// The `verify` subcommand
+    #[options(help = "verify a proof-of-reproducibility offline")]
+    Verify(VerifyCmd),
+
+    /// The `diff` subcommand
+    #[options(help = "show how dissenting builders' artifacts differ")]
+    Diff(DiffCmd),
 }
 
 impl Configurable<SynchronicityConfig> for SynchronicityCmd {
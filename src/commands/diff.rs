@@ -0,0 +1,295 @@
+//! `diff` subcommand - diagnose why builders disagreed about a build
+
+use crate::{
+    artifact_service::ArtifactClient,
+    client::Client,
+    config::ArtifactsConfig,
+    diff::{BlockDigests, FileChange, ManifestDiff},
+    manifest::ManifestEntry,
+    prelude::*,
+    state::{Build, BuildId},
+    verdict::{BuildResult, VerdictStatus},
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{collections::BTreeMap, process::exit};
+use synchro::{
+    config::NodeConfig, crypto::ed25519::Ed25519PublicKey, types::account_address::AccountAddress,
+};
+
+/// `diff` subcommand
+#[derive(Command, Debug, Options)]
+pub struct DiffCmd {
+    /// Summarize byte-level differences in modified artifacts
+    #[options(
+        short = "b",
+        long = "binary",
+        help = "summarize binary differences (queries builders' artifact services)"
+    )]
+    binary: bool,
+
+    /// Build ID (hex)
+    #[options(free)]
+    build_id: Vec<String>,
+}
+
+impl Runnable for DiffCmd {
+    /// Compare each dissenting builder's artifacts against the majority's
+    fn run(&self) {
+        let build_id = self.parse_build_id();
+        let cfg = app_config();
+
+        let client = Client::new(&cfg).unwrap_or_else(|e| {
+            status_err!("couldn't initialize client: {}", e);
+            exit(1);
+        });
+
+        let build = match client.build(&build_id) {
+            Ok(Some(build)) => build,
+            Ok(None) => {
+                status_err!("no such build: {}", build_id);
+                exit(1);
+            }
+            Err(e) => {
+                status_err!("couldn't get build: {}", e);
+                exit(1);
+            }
+        };
+
+        let verdict = match client.verdict(&build.request) {
+            Ok(Some(verdict)) => verdict,
+            Ok(None) => {
+                status_err!("no verdict has been reached on build {}", build_id);
+                exit(1);
+            }
+            Err(e) => {
+                status_err!("couldn't get verdict: {}", e);
+                exit(1);
+            }
+        };
+
+        let (majority, dissenters) = match &verdict.status {
+            VerdictStatus::Reproduced {
+                source_digest,
+                artifact_root,
+                dissenters,
+                ..
+            } => (
+                BuildResult {
+                    source_digest: *source_digest,
+                    artifact_root: *artifact_root,
+                },
+                dissenters,
+            ),
+            VerdictStatus::Diverged {
+                source_digest,
+                plurality_root,
+                dissenters,
+            } => (
+                BuildResult {
+                    source_digest: *source_digest,
+                    artifact_root: *plurality_root,
+                },
+                dissenters,
+            ),
+            VerdictStatus::InsufficientParticipation { revealed } => {
+                status_err!(
+                    "build {} had insufficient participation ({} reveal(s)): nothing to compare",
+                    build_id,
+                    revealed
+                );
+                exit(1);
+            }
+        };
+
+        status_ok!(
+            "Comparing",
+            "{} dissenter(s) against majority root {}",
+            dissenters.len(),
+            hex::encode(majority.artifact_root)
+        );
+
+        let differ = Differ {
+            build_id,
+            build: &build,
+            majority,
+            artifacts: &cfg.artifacts,
+            builder_keys: builder_keys(&cfg.load_node_config()),
+            binary: self.binary,
+        };
+
+        for (dissenter, result) in dissenters {
+            differ.print(dissenter, result);
+        }
+    }
+}
+
+impl DiffCmd {
+    /// Parse the build ID given on the command line
+    fn parse_build_id(&self) -> BuildId {
+        if self.build_id.len() != 1 {
+            status_err!("usage: synchronicity diff [--binary] BUILD_ID");
+            exit(1);
+        }
+
+        let bytes = hex::decode(&self.build_id[0]).unwrap_or_default();
+        let mut build_id = BuildId([0u8; 32]);
+
+        if bytes.len() != build_id.0.len() {
+            status_err!("invalid build ID: {:?}", self.build_id[0]);
+            exit(1);
+        }
+
+        build_id.0.copy_from_slice(&bytes);
+        build_id
+    }
+}
+
+/// Compares dissenters' artifacts against the majority's
+struct Differ<'a> {
+    /// Build being compared
+    build_id: BuildId,
+
+    /// Record of the build
+    build: &'a Build,
+
+    /// Sources built and artifact root revealed by the majority of builders
+    majority: BuildResult,
+
+    /// Artifact service configuration
+    artifacts: &'a ArtifactsConfig,
+
+    /// Consensus public keys of the group's builders, which their artifact
+    /// services sign responses with
+    builder_keys: BTreeMap<AccountAddress, Ed25519PublicKey>,
+
+    /// Summarize byte-level differences
+    binary: bool,
+}
+
+impl<'a> Differ<'a> {
+    /// Print the differences between a dissenter's artifacts and the
+    /// majority's
+    fn print(&self, dissenter: &AccountAddress, result: &BuildResult) {
+        println!(
+            "dissenter: {} {}",
+            dissenter,
+            hex::encode(result.artifact_root)
+        );
+
+        // Artifacts built from different sources are expected to differ, so
+        // report that first
+        if result.source_digest != self.majority.source_digest {
+            println!(
+                "  source mismatch: built {} (majority built {})",
+                hex::encode(result.source_digest),
+                hex::encode(self.majority.source_digest)
+            );
+        }
+
+        let (majority, theirs) = match (
+            self.build.manifests.get(&self.majority.artifact_root),
+            self.build.manifests.get(&result.artifact_root),
+        ) {
+            (Some(majority), Some(theirs)) => (majority, theirs),
+            _ => {
+                status_warn!("manifest missing from build record");
+                return;
+            }
+        };
+
+        for change in &ManifestDiff::between(majority, theirs).changes {
+            println!("  {}", change);
+
+            if self.binary {
+                if let FileChange::Modified { base, other } = change {
+                    self.print_binary_diff(dissenter, base, other);
+                }
+            }
+        }
+    }
+
+    /// Print a summary of the byte-level differences in an artifact
+    fn print_binary_diff(
+        &self,
+        dissenter: &AccountAddress,
+        majority_entry: &ManifestEntry,
+        dissenter_entry: &ManifestEntry,
+    ) {
+        let majority_builders = self
+            .build
+            .reveals
+            .iter()
+            .filter(|(builder, root)| {
+                **root == self.majority.artifact_root
+                    && self.build.sources.get(builder) == Some(&self.majority.source_digest)
+            })
+            .map(|(builder, _)| builder);
+
+        let majority = majority_builders
+            .filter_map(|builder| self.block_digests(builder, majority_entry))
+            .next();
+
+        let theirs = self.block_digests(dissenter, dissenter_entry);
+
+        match (majority, theirs) {
+            (Some(majority), Some(theirs)) => println!("    {}", majority.compare(&theirs)),
+            (None, _) => println!("    (no majority builder is serving this artifact)"),
+            (_, None) => println!("    (dissenter isn't serving this artifact)"),
+        }
+    }
+
+    /// Fetch the block digests of an artifact from a builder's artifact
+    /// service, if it's configured and still retains the build.
+    ///
+    /// Block digests are only checked against the size of the artifact the
+    /// builder revealed, so the resulting summary is advisory.
+    fn block_digests(
+        &self,
+        builder: &AccountAddress,
+        entry: &ManifestEntry,
+    ) -> Option<BlockDigests> {
+        let addr = self.artifacts.peer_addr(builder)?;
+
+        let public_key = match self.builder_keys.get(builder) {
+            Some(public_key) => public_key.clone(),
+            None => {
+                status_warn!("no consensus key known for {} ({})", builder, addr);
+                return None;
+            }
+        };
+
+        match ArtifactClient::new(addr, public_key).block_digests(&self.build_id, &entry.path) {
+            Ok(Some(digests)) if digests.size == entry.size => Some(digests),
+            Ok(Some(_)) => {
+                status_warn!(
+                    "{} ({}) is serving a different {}",
+                    builder,
+                    addr,
+                    entry.path
+                );
+                None
+            }
+            Ok(None) => None,
+            Err(e) => {
+                status_warn!("couldn't query {} ({}): {}", builder, addr, e);
+                None
+            }
+        }
+    }
+}
+
+/// Get the consensus public keys of the group's validators from the node
+/// config, keyed by the account each one builds as (which is derived from
+/// its consensus key)
+fn builder_keys(node_config: &NodeConfig) -> BTreeMap<AccountAddress, Ed25519PublicKey> {
+    node_config
+        .consensus
+        .consensus_peers
+        .peers
+        .values()
+        .map(|peer| {
+            let public_key = peer.consensus_pubkey.clone();
+            (AccountAddress::from_public_key(&public_key), public_key)
+        })
+        .collect()
+}
@@ -0,0 +1,236 @@
+//! `genesis` subcommand - form a group from the `peer_info.toml` of each node
+
+use crate::{
+    prelude::*,
+    state::{self, Params},
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+use synchro::{
+    config::{peer_info, NodeConfig, PeerInfo, PeerInfoSet, PersistableConfig},
+    types::{account_address::AccountAddress, transaction::Transaction},
+};
+
+/// Name of the genesis transaction file written into each base directory
+pub const GENESIS_FILE: &str = "genesis.blob";
+
+/// `genesis` subcommand
+#[derive(Command, Debug, Options)]
+pub struct GenesisCmd {
+    /// Directory containing a `peer_info.toml` file from each node
+    #[options(
+        short = "p",
+        long = "peers",
+        help = "directory containing each node's peer_info.toml (as *.toml)"
+    )]
+    peers: Option<PathBuf>,
+
+    /// Number of matching reveals needed to consider a build reproduced
+    #[options(
+        short = "t",
+        long = "threshold",
+        help = "matching reveals required (default: 2f+1 of the builders)"
+    )]
+    threshold: Option<usize>,
+
+    /// Base directories of the nodes to configure
+    #[options(free)]
+    base_dirs: Vec<PathBuf>,
+}
+
+impl Runnable for GenesisCmd {
+    /// Configure each node as a member of the group, with identical genesis
+    fn run(&self) {
+        let peers_dir = match &self.peers {
+            Some(peers_dir) if !self.base_dirs.is_empty() => peers_dir,
+            _ => {
+                status_err!("usage: synchronicity genesis --peers DIR BASE_DIR...");
+                exit(1);
+            }
+        };
+
+        let peer_info_set = load_peers(peers_dir);
+        let genesis = self.genesis_transaction(&peer_info_set);
+
+        for base_dir in &self.base_dirs {
+            configure_node(base_dir, &peer_info_set, &genesis);
+        }
+    }
+}
+
+impl GenesisCmd {
+    /// Build the genesis transaction, which makes every validator a builder.
+    ///
+    /// Builders submit transactions from the account derived from their
+    /// consensus key, which is what the executor authenticates them against.
+    fn genesis_transaction(&self, peer_info_set: &PeerInfoSet) -> Vec<u8> {
+        let builders = peer_info_set
+            .validators()
+            .map(|peer| AccountAddress::from_public_key(&peer.consensus.consensus_pubkey))
+            .collect::<BTreeSet<_>>();
+
+        if builders.is_empty() {
+            status_err!("no validators among the peers");
+            exit(1);
+        }
+
+        if let Some(threshold) = self.threshold {
+            if threshold == 0 || threshold > builders.len() {
+                status_err!(
+                    "threshold must be between 1 and the number of validators ({})",
+                    builders.len()
+                );
+                exit(1);
+            }
+        }
+
+        let params = Params {
+            builders,
+            threshold: self.threshold,
+            ..Params::default()
+        };
+
+        let transaction = Transaction::WriteSet(state::genesis_write_set(&params));
+        bincode::serialize(&transaction).expect("genesis serialization failed")
+    }
+}
+
+/// Load every `*.toml` file in a directory as a `PeerInfo`
+fn load_peers(peers_dir: &Path) -> PeerInfoSet {
+    let dir_entries = fs::read_dir(peers_dir).unwrap_or_else(|e| {
+        status_err!("couldn't read {}: {}", peers_dir.display(), e);
+        exit(1);
+    });
+
+    let mut peers = vec![];
+
+    for dir_entry in dir_entries {
+        let path = dir_entry
+            .unwrap_or_else(|e| {
+                status_err!("couldn't read {}: {}", peers_dir.display(), e);
+                exit(1);
+            })
+            .path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            continue;
+        }
+
+        let peer = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|toml_string| {
+                toml::from_str::<PeerInfo>(&toml_string).map_err(|e| e.to_string())
+            })
+            .unwrap_or_else(|e| {
+                status_err!("couldn't load {}: {}", path.display(), e);
+                exit(1);
+            });
+
+        peers.push(peer);
+    }
+
+    let peer_info_set = PeerInfoSet::new(peers);
+
+    if peer_info_set.peers.is_empty() {
+        status_err!("no peer info files in {}", peers_dir.display());
+        exit(1);
+    }
+
+    for pair in peer_info_set.peers.windows(2) {
+        if pair[0].id == pair[1].id {
+            status_err!(
+                "duplicate peer ID in {}: {}",
+                peers_dir.display(),
+                pair[0].id
+            );
+            exit(1);
+        }
+    }
+
+    status_ok!(
+        "Loaded",
+        "{} peer(s) from {}",
+        peer_info_set.peers.len(),
+        peers_dir.display()
+    );
+
+    peer_info_set
+}
+
+/// Update a node's `node.config.toml` with the group's peers and genesis,
+/// and write the genesis transaction and `validators.toml` alongside it
+fn configure_node(base_dir: &Path, peer_info_set: &PeerInfoSet, genesis: &[u8]) {
+    let node_config_path = base_dir.join("node.config.toml");
+
+    if !node_config_path.exists() {
+        status_err!(
+            "{} not found (run `synchronicity init` first)",
+            node_config_path.display()
+        );
+        exit(1);
+    }
+
+    let mut node_config = NodeConfig::load_config(&node_config_path);
+
+    let peer_id = match node_config.networks.get(0) {
+        Some(network) => network.peer_id.clone(),
+        None => {
+            status_err!("no networks in {}", node_config_path.display());
+            exit(1);
+        }
+    };
+
+    let peer_info = match peer_info_set.get(&peer_id) {
+        Some(peer_info) => peer_info,
+        None => {
+            status_err!(
+                "{} (peer {}) isn't one of the peers",
+                base_dir.display(),
+                peer_id
+            );
+            exit(1);
+        }
+    };
+
+    let seed_peers = peer_info_set.seed_peers(&peer_id).unwrap_or_else(|e| {
+        status_err!("invalid seed address: {}", e);
+        exit(1);
+    });
+
+    node_config.consensus.consensus_peers = peer_info_set.consensus_peers();
+    node_config.networks[0].network_peers = peer_info_set.network_peers();
+    node_config.networks[0].seed_peers = seed_peers;
+
+    // Full nodes sync the ledger from the validators
+    if !peer_info.is_validator() {
+        node_config.state_sync.upstream_peers.upstream_peers = peer_info_set
+            .validators()
+            .map(|validator| validator.id.clone())
+            .collect();
+    }
+
+    let genesis_path = base_dir.join(GENESIS_FILE);
+
+    fs::write(&genesis_path, genesis).unwrap_or_else(|e| {
+        status_err!("couldn't write {}: {}", genesis_path.display(), e);
+        exit(1);
+    });
+
+    status_ok!("Generated", "{}", genesis_path.display());
+
+    node_config.execution.genesis_file_location = genesis_path.display().to_string();
+    node_config.save_config(&node_config_path);
+    status_ok!("Updated", "{}", node_config_path.display());
+
+    // Proofs are checked against `validators.toml`, so it only lists the
+    // peers who sign the ledger
+    let validators = PeerInfoSet::new(peer_info_set.validators().cloned().collect());
+    let validators_path = base_dir.join(peer_info::VALIDATORS_FILENAME);
+    validators.save_config(&validators_path);
+    status_ok!("Generated", "{}", validators_path.display());
+}
@@ -3,11 +3,12 @@
 use crate::{config::CONFIG_FILE, prelude::*};
 use abscissa_core::{Command, Options, Runnable};
 use std::{
-    fs,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
     path::{Path, PathBuf},
     process::exit,
 };
-use synchro::config::{self, peer_info, KeySeed};
+use synchro::config::{self, peer_info, KeySeed, RoleType};
 
 /// Derivation component used when computing seed
 pub const DERIVATION_COMPONENT: &[u8] = b"synchronicity";
@@ -19,6 +20,30 @@ pub struct InitCmd {
     #[options(short = "l", long = "listen", help = "listen on this IP address")]
     listen_address: Option<String>,
 
+    /// Role of the node
+    #[options(
+        short = "r",
+        long = "role",
+        help = "node role: validator (default) or full-node"
+    )]
+    role: Option<String>,
+
+    /// Restore keys from a BIP39 phrase in a file (`-` for stdin)
+    #[options(
+        short = "m",
+        long = "from-mnemonic",
+        help = "restore keys from the BIP39 phrase in this file (- for stdin)"
+    )]
+    from_mnemonic: Option<PathBuf>,
+
+    /// Print the BIP39 phrase keys are derived from
+    #[options(long = "print-mnemonic", help = "print the BIP39 phrase")]
+    print_mnemonic: bool,
+
+    /// Save the BIP39 phrase keys are derived from to a file
+    #[options(long = "save-mnemonic", help = "save the BIP39 phrase to this file")]
+    save_mnemonic: Option<PathBuf>,
+
     /// Path to the base directory
     #[options(free)]
     base_dir: Vec<PathBuf>,
@@ -28,12 +53,12 @@ impl Runnable for InitCmd {
     /// Initialize application configuration.
     fn run(&self) {
         let base_dir = self.prepare_base_dir();
-
-        // TODO(tarcieri): support for reusing a previously generated `KeySeed`
-        let key_seed = KeySeed::generate();
+        let role = self.role();
+        let key_seed = self.key_seed();
+        self.output_mnemonic(&key_seed);
 
         self.generate_synchronicity_toml(&base_dir);
-        self.generate_libra_configs(&base_dir, key_seed);
+        self.generate_libra_configs(&base_dir, key_seed, role);
     }
 }
 
@@ -59,6 +84,71 @@ impl InitCmd {
         base_dir
     }
 
+    /// Get the role of the node being initialized
+    pub fn role(&self) -> RoleType {
+        match self.role.as_ref().map(String::as_str) {
+            None | Some("validator") => RoleType::Validator,
+            Some("full-node") => RoleType::FullNode,
+            Some(other) => {
+                status_err!("unknown role: {} (expected validator or full-node)", other);
+                exit(1);
+            }
+        }
+    }
+
+    /// Get the `KeySeed` to derive keys from: either restored from the phrase
+    /// given with `--from-mnemonic`, or freshly generated
+    pub fn key_seed(&self) -> KeySeed {
+        let path = match &self.from_mnemonic {
+            Some(path) => path,
+            None => return KeySeed::generate(),
+        };
+
+        let result = if path == Path::new("-") {
+            let mut phrase = String::new();
+            io::stdin().read_to_string(&mut phrase).map(|_| phrase)
+        } else {
+            fs::read_to_string(path)
+        };
+
+        let phrase = result.unwrap_or_else(|e| {
+            status_err!("couldn't read BIP39 phrase from {}: {}", path.display(), e);
+            exit(1);
+        });
+
+        let key_seed = KeySeed::from_phrase(&phrase).unwrap_or_else(|e| {
+            status_err!("{}", e);
+            exit(1);
+        });
+
+        status_ok!("Restored", "key seed from BIP39 phrase");
+        key_seed
+    }
+
+    /// Print and/or save the BIP39 phrase for a `KeySeed` as requested
+    pub fn output_mnemonic(&self, key_seed: &KeySeed) {
+        if self.print_mnemonic {
+            println!("{}", key_seed.phrase());
+        }
+
+        if let Some(path) = &self.save_mnemonic {
+            write_secret(path, key_seed.phrase()).unwrap_or_else(|e| {
+                status_err!("couldn't write {}: {}", path.display(), e);
+                exit(1);
+            });
+
+            status_ok!("Saved", "BIP39 phrase to {}", path.display());
+        }
+
+        // A freshly generated phrase which isn't output can never be recovered
+        if self.from_mnemonic.is_none() && !self.print_mnemonic && self.save_mnemonic.is_none() {
+            status_warn!(
+                "BIP39 phrase not saved: keys can't be recovered \
+                 (use --print-mnemonic or --save-mnemonic)"
+            );
+        }
+    }
+
     /// Generate Synchronicity-specific config file (i.e. `synchronicity.toml`)
     pub fn generate_synchronicity_toml(&self, base_dir: &Path) {
         // TODO(tarcieri): better templating
@@ -66,9 +156,11 @@ impl InitCmd {
             "# serendipity.toml: configuration file for Serendipity\n\
              node_config = \"{}\"\n\
              scratch_dir = \"{}\"\n\
+             storage_dir = \"{}\"\n\
              ",
             base_dir.join("node.config.toml").display(),
-            base_dir.join("scratch/").display()
+            base_dir.join("scratch/").display(),
+            base_dir.join("db/").display()
         );
 
         let config_path = base_dir.join(CONFIG_FILE);
@@ -82,9 +174,9 @@ impl InitCmd {
     }
 
     /// Generate configuration files specific to Libra
-    pub fn generate_libra_configs(&self, base_dir: &Path, key_seed: KeySeed) {
+    pub fn generate_libra_configs(&self, base_dir: &Path, key_seed: KeySeed, role: RoleType) {
         let mut builder = config::Builder::new(key_seed);
-        builder.with_output_dir(base_dir);
+        builder.with_output_dir(base_dir).with_role(role);
 
         if let Some(listen_addr) = &self.listen_address {
             builder.with_listen_address(listen_addr);
@@ -132,3 +224,24 @@ impl InitCmd {
         );
     }
 }
+
+/// Write a secret to a new file which is only readable by its owner
+#[cfg(unix)]
+fn write_secret(path: &Path, secret: &str) -> io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+
+    writeln!(file, "{}", secret)
+}
+
+/// Write a secret to a new file
+#[cfg(not(unix))]
+fn write_secret(path: &Path, secret: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    writeln!(file, "{}", secret)
+}
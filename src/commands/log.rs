@@ -0,0 +1,230 @@
+//! `log` subcommand - inspect the reproducibility log
+
+use crate::{log::LogSnapshot, prelude::*, state, verdict::Verdict};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process::exit};
+use synchro::{
+    log::{Checkpoint, Hash},
+    proof::{ProofFile, ValidatorSet},
+};
+
+/// `log` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum LogCmd {
+    /// The `log checkpoint` subcommand
+    #[options(help = "show the current size and root hash of the log")]
+    Checkpoint(CheckpointCmd),
+
+    /// The `log inclusion` subcommand
+    #[options(help = "prove a verdict is included in the log")]
+    Inclusion(InclusionCmd),
+
+    /// The `log consistency` subcommand
+    #[options(help = "prove the log is an append-only extension of a checkpoint")]
+    Consistency(ConsistencyCmd),
+}
+
+/// `log checkpoint` subcommand
+#[derive(Command, Debug, Options)]
+pub struct CheckpointCmd {}
+
+impl Runnable for CheckpointCmd {
+    /// Print the current checkpoint
+    fn run(&self) {
+        let snapshot = fetch_snapshot();
+        print_checkpoint(&signed_checkpoint(&snapshot, &load_validator_set()));
+    }
+}
+
+/// `log inclusion` subcommand
+#[derive(Command, Debug, Options)]
+pub struct InclusionCmd {
+    /// Path to write a proof file to
+    #[options(short = "o", long = "output", help = "write a proof file to this path")]
+    output: Option<PathBuf>,
+
+    /// Index of the leaf to prove inclusion of
+    #[options(free)]
+    leaf_index: Vec<u64>,
+}
+
+impl Runnable for InclusionCmd {
+    /// Generate and verify an inclusion proof
+    fn run(&self) {
+        if self.leaf_index.len() != 1 {
+            status_err!("usage: synchronicity log inclusion LEAF_INDEX");
+            exit(1);
+        }
+
+        let validator_set = load_validator_set();
+        let snapshot = fetch_snapshot();
+        let checkpoint = signed_checkpoint(&snapshot, &validator_set);
+
+        let (leaf, proof) = snapshot
+            .inclusion_proof(self.leaf_index[0])
+            .unwrap_or_else(|e| {
+                status_err!("{}", e);
+                exit(1);
+            });
+
+        let proof_file = ProofFile {
+            address: state::log_account(),
+            log_path: state::LOG_PATH.to_vec(),
+            leaf,
+            proof,
+            validator_set: validator_set.clone(),
+        };
+
+        // Check the whole proof, down to the quorum signatures on the ledger
+        // info, against the validator set we were configured with
+        if let Err(e) = proof_file.verify_with(&validator_set) {
+            status_err!("inclusion proof failed to verify: {}", e);
+            exit(1);
+        }
+
+        match Verdict::from_log_leaf(&proof_file.leaf) {
+            Ok(verdict) => status_ok!(
+                "Included",
+                "{} {} ({}): {:?}",
+                verdict.request.crate_name,
+                verdict.request.version,
+                verdict.request.env.target,
+                verdict.status
+            ),
+            Err(e) => status_warn!("couldn't decode verdict: {}", e),
+        }
+
+        print_checkpoint(&checkpoint);
+        println!("leaf_index: {}", proof_file.proof.inclusion.leaf_index);
+
+        for hash in &proof_file.proof.inclusion.audit_path {
+            println!("audit_path: {}", hex::encode(hash));
+        }
+
+        if let Some(output) = &self.output {
+            proof_file.save(output).unwrap_or_else(|e| {
+                status_err!("couldn't write {}: {}", output.display(), e);
+                exit(1);
+            });
+
+            status_ok!("Generated", "{}", output.display());
+        }
+    }
+}
+
+/// `log consistency` subcommand
+#[derive(Command, Debug, Options)]
+pub struct ConsistencyCmd {
+    /// Size and root hash of a previously saved checkpoint
+    #[options(free)]
+    checkpoint: Vec<String>,
+}
+
+impl Runnable for ConsistencyCmd {
+    /// Generate and verify a consistency proof
+    fn run(&self) {
+        let old = self.parse_checkpoint();
+        let snapshot = fetch_snapshot();
+        let new = signed_checkpoint(&snapshot, &load_validator_set());
+
+        let proof = snapshot.consistency_proof(old.size).unwrap_or_else(|e| {
+            status_err!("{}", e);
+            exit(1);
+        });
+
+        if let Err(e) = proof.verify(&old.root, &new.root) {
+            status_err!(
+                "log at size {} is NOT consistent with checkpoint at size {}: {}",
+                new.size,
+                old.size,
+                e
+            );
+            exit(1);
+        }
+
+        status_ok!(
+            "Consistent",
+            "log at size {} extends checkpoint at size {}",
+            new.size,
+            old.size
+        );
+
+        print_checkpoint(&new);
+
+        for hash in &proof.path {
+            println!("path: {}", hex::encode(hash));
+        }
+    }
+}
+
+impl ConsistencyCmd {
+    /// Parse the checkpoint given on the command line
+    fn parse_checkpoint(&self) -> Checkpoint {
+        if self.checkpoint.len() != 2 {
+            status_err!("usage: synchronicity log consistency OLD_SIZE OLD_ROOT_HEX");
+            exit(1);
+        }
+
+        let size = self.checkpoint[0].parse().unwrap_or_else(|e| {
+            status_err!("invalid log size {:?}: {}", self.checkpoint[0], e);
+            exit(1);
+        });
+
+        let root = parse_hash(&self.checkpoint[1]).unwrap_or_else(|| {
+            status_err!("invalid root hash: {:?}", self.checkpoint[1]);
+            exit(1);
+        });
+
+        Checkpoint { size, root }
+    }
+}
+
+/// Fetch a snapshot of the log from the node's storage
+fn fetch_snapshot() -> LogSnapshot {
+    let storage = app_config().storage_read_client();
+
+    LogSnapshot::fetch(&storage).unwrap_or_else(|e| {
+        status_err!("couldn't fetch reproducibility log: {}", e);
+        exit(1);
+    })
+}
+
+/// Load the validator set from the node's consensus peers
+fn load_validator_set() -> ValidatorSet {
+    let node_config = app_config().load_node_config();
+
+    ValidatorSet::from_consensus_peers(&node_config.consensus.consensus_peers).unwrap_or_else(|e| {
+        status_err!("couldn't load validator set: {}", e);
+        exit(1);
+    })
+}
+
+/// Get a snapshot's checkpoint from the ledger state signed by a quorum of
+/// the validator set
+fn signed_checkpoint(snapshot: &LogSnapshot, validator_set: &ValidatorSet) -> Checkpoint {
+    snapshot
+        .signed_checkpoint(validator_set)
+        .unwrap_or_else(|e| {
+            status_err!("log snapshot failed to verify: {}", e);
+            exit(1);
+        })
+}
+
+/// Print a checkpoint
+fn print_checkpoint(checkpoint: &Checkpoint) {
+    println!("size: {}", checkpoint.size);
+    println!("root: {}", hex::encode(checkpoint.root));
+}
+
+/// Parse a hex-encoded hash
+fn parse_hash(hex_str: &str) -> Option<Hash> {
+    let bytes = hex::decode(hex_str).ok()?;
+
+    if bytes.len() != 32 {
+        return None;
+    }
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&bytes);
+    Some(hash)
+}
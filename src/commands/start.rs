@@ -5,8 +5,19 @@
 //
 // Copyright (c) The Libra Core Contributors
 
-use crate::{executor::SynchronicityExecutor, prelude::*, verifier::VerifyProvider};
+use crate::{
+    artifact_service::ArtifactServer,
+    client::Client,
+    error::{Error, ErrorKind},
+    executor::SynchronicityExecutor,
+    participant::Participant,
+    prelude::*,
+    verifier::VerifyProvider,
+    watcher::IndexWatcher,
+    worker::Worker,
+};
 use abscissa_core::{Command, Options, Runnable};
+use std::{process::exit, sync::Arc, thread};
 use synchro::{config::NodeConfig, Launcher, Node};
 
 /// `start` subcommand
@@ -16,9 +27,36 @@ pub struct StartCmd {}
 impl Runnable for StartCmd {
     /// Start the application.
     fn run(&self) {
-        let verify_provider = VerifyProvider::new();
-        let launcher = Launcher::new(self.load_node_config(), verify_provider).unwrap();
-        let _node: Node<SynchronicityExecutor> = launcher.launch().unwrap();
+        let node = self.launch_node().unwrap_or_else(|e| {
+            status_err!("couldn't start node: {}", e);
+            exit(1);
+        });
+
+        self.start_artifact_service();
+
+        // Full nodes only mirror the ledger: they don't build or submit
+        if node.consensus.is_some() {
+            // Both submit from the node's account, so they share a client to
+            // keep from reusing sequence numbers
+            let client = Arc::new(Client::new(&app_config()).unwrap_or_else(|e| {
+                status_err!("couldn't initialize client: {}", e);
+                exit(1);
+            }));
+
+            self.start_participant(&client);
+            self.start_watcher(&client);
+            status_ok!("Started", "validator (stop with SIGINT or SIGTERM)");
+        } else {
+            if app_config().watcher.is_some() {
+                status_warn!("crates.io index watcher disabled on full nodes");
+            }
+
+            status_ok!("Started", "full node (stop with SIGINT or SIGTERM)");
+        }
+
+        let status = node.run_until_shutdown();
+        status_ok!("Stopped", "node");
+        exit(status);
     }
 }
 
@@ -28,4 +66,78 @@ impl StartCmd {
         let cfg = app_config();
         cfg.load_node_config()
     }
+
+    /// Launch the Libra node this application runs on
+    fn launch_node(&self) -> Result<Node<SynchronicityExecutor>, Error> {
+        let verify_provider = VerifyProvider::new();
+
+        Launcher::new(self.load_node_config(), verify_provider)
+            .and_then(|launcher| launcher.launch())
+            .map_err(|e| ErrorKind::Launch.context(e).into())
+    }
+
+    /// Serve digests of retained artifacts to other builders, if configured
+    fn start_artifact_service(&self) {
+        let cfg = app_config();
+
+        let listen_addr = match cfg.artifacts.listen_addr {
+            Some(addr) => addr,
+            None => return,
+        };
+
+        // Responses are signed with the consensus key, so peers can tell
+        // they come from this builder
+        let private_key = match cfg.load_consensus_key() {
+            Ok(private_key) => private_key,
+            Err(e) => {
+                status_warn!("artifact service disabled: {}", e);
+                return;
+            }
+        };
+
+        match ArtifactServer::new(&cfg.scratch_dir, private_key).spawn(listen_addr) {
+            Ok(addr) => status_ok!("Serving", "artifact digests on {}", addr),
+            Err(e) => status_warn!("artifact service disabled: {}", e),
+        }
+    }
+
+    /// Start participating in builds in a background thread.
+    ///
+    /// If the build backend can't be initialized (e.g. Docker isn't
+    /// available) the node still runs, but doesn't build anything.
+    fn start_participant(&self, client: &Arc<Client>) {
+        let cfg = app_config();
+
+        let worker = match Worker::from_config(&cfg) {
+            Ok(worker) => worker,
+            Err(e) => {
+                status_warn!("not participating in builds: {}", e);
+                return;
+            }
+        };
+
+        let client = Arc::clone(client);
+        thread::spawn(move || Participant::new(client, worker).run());
+    }
+
+    /// Watch the crates.io index for new versions in a background thread,
+    /// if configured
+    fn start_watcher(&self, client: &Arc<Client>) {
+        let cfg = app_config();
+
+        let watcher_config = match &cfg.watcher {
+            Some(watcher_config) => watcher_config,
+            None => return,
+        };
+
+        let client = Arc::clone(client);
+        let mut watcher = IndexWatcher::from_config(client, watcher_config, &cfg.scratch_dir)
+            .unwrap_or_else(|e| {
+                status_err!("couldn't start crates.io index watcher: {}", e);
+                exit(1);
+            });
+
+        let poll_interval = watcher_config.poll_interval();
+        thread::spawn(move || watcher.run(poll_interval));
+    }
 }
@@ -0,0 +1,377 @@
+//! `submit` subcommand - request that the group build a crate version
+
+use crate::{
+    client::Client,
+    payload::{BuildEnv, BuildRequest, Payload, Profile, Toolchain},
+    prelude::*,
+    state::{Digest, Phase},
+    verdict::{Verdict, VerdictStatus},
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{
+    process::exit,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Target triple to build for if none is given
+pub const DEFAULT_TARGET: &str = "x86_64-unknown-linux-gnu";
+
+/// Toolchain channel to build with if none is given
+pub const DEFAULT_CHANNEL: &str = "stable";
+
+/// Path prefix build directories are remapped to if none is given
+pub const DEFAULT_REMAP_PATH_PREFIX: &str = "/build";
+
+/// How often to poll the node while waiting
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `submit` subcommand
+#[derive(Command, Debug, Options)]
+pub struct SubmitCmd {
+    /// Registry alias or index URL
+    #[options(
+        short = "r",
+        long = "registry",
+        help = "registry alias or index URL (default: crates-io)"
+    )]
+    registry: Option<String>,
+
+    /// Target triple to build for
+    #[options(short = "t", long = "target", help = "target triple to build for")]
+    target: Option<String>,
+
+    /// Rust toolchain channel to build with
+    #[options(
+        short = "T",
+        long = "toolchain",
+        help = "toolchain channel (default: stable)"
+    )]
+    toolchain: Option<String>,
+
+    /// Release date of the toolchain
+    #[options(long = "toolchain-date", help = "toolchain release date (YYYY-MM-DD)")]
+    toolchain_date: Option<String>,
+
+    /// Commit hash of the toolchain's `rustc`
+    #[options(long = "toolchain-hash", help = "rustc commit hash")]
+    toolchain_hash: Option<String>,
+
+    /// Cargo features to enable
+    #[options(
+        short = "F",
+        long = "features",
+        help = "comma-separated features to enable"
+    )]
+    features: Option<String>,
+
+    /// Disable the crate's default features
+    #[options(long = "no-default-features", help = "disable default features")]
+    no_default_features: bool,
+
+    /// Build with the dev profile instead of release
+    #[options(long = "debug", help = "build with the dev profile")]
+    debug: bool,
+
+    /// Value of `SOURCE_DATE_EPOCH`
+    #[options(long = "source-date-epoch", help = "SOURCE_DATE_EPOCH (default: 0)")]
+    source_date_epoch: Option<u64>,
+
+    /// Path prefix to remap the build directory to
+    #[options(long = "remap-path-prefix", help = "remap build paths to this prefix")]
+    remap_path_prefix: Option<String>,
+
+    /// Don't remap the build directory
+    #[options(long = "no-remap", help = "don't remap build paths")]
+    no_remap: bool,
+
+    /// Additional `RUSTFLAGS`
+    #[options(long = "rustflag", help = "additional RUSTFLAG (repeatable)")]
+    rustflags: Vec<String>,
+
+    /// SHA-256 digest of the `Cargo.lock` to build with as-is
+    #[options(
+        long = "lockfile",
+        help = "hex SHA-256 of the crate's Cargo.lock (builds with --locked)"
+    )]
+    lockfile: Option<String>,
+
+    /// Wait for the verdict
+    #[options(short = "w", long = "wait", help = "wait for and print the verdict")]
+    wait: bool,
+
+    /// Give up waiting after this many seconds
+    #[options(
+        long = "timeout",
+        help = "give up waiting after this many seconds (implies --wait)"
+    )]
+    timeout: Option<u64>,
+
+    /// Crate name and version
+    #[options(free)]
+    args: Vec<String>,
+}
+
+impl Runnable for SubmitCmd {
+    /// Submit a build request
+    fn run(&self) {
+        let request = self.build_request();
+
+        if let Err(e) = request.validate() {
+            status_err!("{}", e);
+            exit(1);
+        }
+
+        let client = Client::new(&app_config()).unwrap_or_else(|e| {
+            status_err!("couldn't initialize client: {}", e);
+            exit(1);
+        });
+
+        let sequence_number = client
+            .submit(&Payload::BuildRequest(request.clone()))
+            .unwrap_or_else(|e| {
+                status_err!("couldn't submit build request: {}", e);
+                exit(1);
+            });
+
+        status_ok!(
+            "Submitted",
+            "{} {} ({}, {}) as build {}",
+            request.crate_name,
+            request.version,
+            request.env.target,
+            request.env.toolchain,
+            request.id()
+        );
+
+        if self.wait || self.timeout.is_some() {
+            let deadline = self
+                .timeout
+                .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+            wait_for_execution(&client, sequence_number, deadline);
+            print_verdict(&wait_for_verdict(&client, &request, deadline));
+        }
+    }
+}
+
+impl SubmitCmd {
+    /// Parse the build request given on the command line
+    fn build_request(&self) -> BuildRequest {
+        if self.args.len() != 2 {
+            status_err!(
+                "usage: synchronicity submit CRATE VERSION [--registry REGISTRY] [--target TARGET] \
+                 [--toolchain CHANNEL] (--toolchain-date DATE | --toolchain-hash HASH)"
+            );
+            exit(1);
+        }
+
+        let registry = match &self.registry {
+            Some(registry) => app_config().resolve_registry(registry).unwrap_or_else(|e| {
+                status_err!("{}", e);
+                exit(1);
+            }),
+            None => None,
+        };
+
+        BuildRequest {
+            crate_name: self.args[0].clone(),
+            version: self.args[1].clone(),
+            registry,
+            env: self.build_env(),
+        }
+    }
+
+    /// Parse the build environment given on the command line
+    fn build_env(&self) -> BuildEnv {
+        let toolchain = Toolchain {
+            channel: self
+                .toolchain
+                .clone()
+                .unwrap_or_else(|| DEFAULT_CHANNEL.to_owned()),
+            date: self.toolchain_date.clone(),
+            commit_hash: self.toolchain_hash.clone(),
+        };
+
+        let features = self
+            .features
+            .iter()
+            .flat_map(|features| features.split(','))
+            .map(str::trim)
+            .filter(|feature| !feature.is_empty())
+            .map(ToOwned::to_owned)
+            .collect();
+
+        let remap_path_prefix = if self.no_remap {
+            None
+        } else {
+            Some(
+                self.remap_path_prefix
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_REMAP_PATH_PREFIX.to_owned()),
+            )
+        };
+
+        BuildEnv {
+            toolchain,
+            target: self
+                .target
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TARGET.to_owned()),
+            features,
+            no_default_features: self.no_default_features,
+            profile: if self.debug {
+                Profile::Dev
+            } else {
+                Profile::Release
+            },
+            source_date_epoch: self.source_date_epoch.unwrap_or(0),
+            remap_path_prefix,
+            rustflags: self.rustflags.clone(),
+            lockfile: self.lockfile.as_ref().map(|hex_digest| {
+                parse_digest(hex_digest).unwrap_or_else(|| {
+                    status_err!("invalid lockfile digest: {:?}", hex_digest);
+                    exit(1);
+                })
+            }),
+        }
+    }
+}
+
+/// Parse a hex-encoded SHA-256 digest
+fn parse_digest(hex_digest: &str) -> Option<Digest> {
+    let bytes = hex::decode(hex_digest).ok()?;
+
+    if bytes.len() != 32 {
+        return None;
+    }
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&bytes);
+    Some(digest)
+}
+
+/// Wait for the transaction with the given sequence number to be executed
+fn wait_for_execution(client: &Client, sequence_number: u64, deadline: Option<Instant>) {
+    loop {
+        match client.sequence_number() {
+            Ok(current) if current > sequence_number => return,
+            Ok(_) => (),
+            Err(e) => status_warn!("couldn't get sequence number: {}", e),
+        }
+
+        sleep_until_next_poll(deadline, "the build request to be executed");
+    }
+}
+
+/// Wait for the group to reach a verdict on a build request
+fn wait_for_verdict(client: &Client, request: &BuildRequest, deadline: Option<Instant>) -> Verdict {
+    loop {
+        match client.verdict(request) {
+            Ok(Some(verdict)) => return verdict,
+            Ok(None) => (),
+            Err(e) => status_warn!("couldn't get verdict: {}", e),
+        }
+
+        // Builds nobody committed to expire without a verdict
+        match client.build(&request.id()) {
+            Ok(Some(build)) if build.phase == Phase::Expired => {
+                status_err!("build expired: no builder committed to it in time");
+                exit(1);
+            }
+            Ok(Some(_)) => (),
+            Ok(None) => {
+                status_err!("build request was rejected");
+                exit(1);
+            }
+            Err(e) => status_warn!("couldn't get build: {}", e),
+        }
+
+        sleep_until_next_poll(deadline, "a verdict");
+    }
+}
+
+/// Sleep until it's time to poll the node again, exiting if the deadline
+/// has already passed
+fn sleep_until_next_poll(deadline: Option<Instant>, waiting_for: &str) {
+    let interval = match deadline {
+        Some(deadline) => {
+            let now = Instant::now();
+
+            if now >= deadline {
+                status_err!("timed out waiting for {}", waiting_for);
+                exit(1);
+            }
+
+            POLL_INTERVAL.min(deadline - now)
+        }
+        None => POLL_INTERVAL,
+    };
+
+    thread::sleep(interval);
+}
+
+/// Print a verdict
+fn print_verdict(verdict: &Verdict) {
+    let request = &verdict.request;
+
+    match &verdict.status {
+        VerdictStatus::Reproduced {
+            source_digest,
+            artifact_root,
+            manifest,
+            builders,
+            dissenters,
+        } => {
+            status_ok!(
+                "Reproduced",
+                "{} {} ({}, {}) by {} builder(s), {} dissenter(s)",
+                request.crate_name,
+                request.version,
+                request.env.target,
+                request.env.toolchain,
+                builders.len(),
+                dissenters.len()
+            );
+
+            println!("source: {}", hex::encode(source_digest));
+            println!("artifact_root: {}", hex::encode(artifact_root));
+
+            for entry in &manifest.entries {
+                println!("artifact: {} {}", hex::encode(entry.sha256), entry.path);
+            }
+        }
+        VerdictStatus::Diverged {
+            plurality_root,
+            dissenters,
+            ..
+        } => {
+            status_err!(
+                "{} {} ({}) diverged: plurality root {} with {} dissenter(s)",
+                request.crate_name,
+                request.version,
+                request.env.target,
+                hex::encode(plurality_root),
+                dissenters.len()
+            );
+        }
+        VerdictStatus::InsufficientParticipation { revealed } => match verdict.threshold {
+            Some(threshold) => status_err!(
+                "{} {} ({}) had insufficient participation: {} reveal(s), {} required",
+                request.crate_name,
+                request.version,
+                request.env.target,
+                revealed,
+                threshold
+            ),
+            None => status_err!(
+                "{} {} ({}) can't be reproduced: the group has no builder set",
+                request.crate_name,
+                request.version,
+                request.env.target
+            ),
+        },
+    }
+
+    println!("log_index: {}", verdict.log_index);
+}
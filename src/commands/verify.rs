@@ -0,0 +1,189 @@
+//! `verify` subcommand - verify a proof-of-reproducibility offline
+
+use crate::{manifest::ManifestEntry, prelude::*, state, verdict::Verdict};
+use abscissa_core::{Command, Options, Runnable};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+use synchro::{
+    config::PeerInfoSet,
+    proof::{ProofFile, ValidatorSet},
+};
+
+/// `verify` subcommand
+#[derive(Command, Debug, Options)]
+pub struct VerifyCmd {
+    /// Path to the proof file
+    #[options(short = "p", long = "proof", help = "path to the .synproof file")]
+    proof: Option<PathBuf>,
+
+    /// Path to the trusted validator set
+    #[options(
+        short = "V",
+        long = "validators",
+        help = "path to trusted validators.toml"
+    )]
+    validators: Option<PathBuf>,
+
+    /// Hex-encoded SHA-256 digest of the artifact (instead of a file)
+    #[options(
+        short = "d",
+        long = "digest",
+        help = "hex SHA-256 digest of the artifact"
+    )]
+    digest: Option<String>,
+
+    /// Path to the artifact
+    #[options(free)]
+    artifact: Vec<PathBuf>,
+}
+
+impl Runnable for VerifyCmd {
+    /// Verify a proof-of-reproducibility. Never touches the network.
+    fn run(&self) {
+        let (proof_path, validators_path) = match (&self.proof, &self.validators) {
+            (Some(proof), Some(validators)) => (proof, validators),
+            _ => self.usage(),
+        };
+
+        let local_artifact = self.local_artifact();
+        let digest = match &local_artifact {
+            Some(entry) => entry.sha256,
+            None => self.artifact_digest(),
+        };
+        let validator_set = load_validator_set(validators_path);
+
+        let proof_file = ProofFile::load(proof_path).unwrap_or_else(|e| {
+            status_err!("couldn't load {}: {}", proof_path.display(), e);
+            exit(1);
+        });
+
+        if proof_file.address != state::log_account() || proof_file.log_path != state::LOG_PATH {
+            status_err!("proof is not for the Synchronicity reproducibility log");
+            exit(1);
+        }
+
+        if let Err(e) = proof_file.verify_with(&validator_set) {
+            status_err!("proof verification failed: {}", e);
+            exit(1);
+        }
+
+        let verdict = Verdict::from_log_leaf(&proof_file.leaf).unwrap_or_else(|e| {
+            status_err!("malformed verdict in proof: {}", e);
+            exit(1);
+        });
+
+        let request = &verdict.request;
+
+        if !verdict.is_reproduced() {
+            status_err!(
+                "{} {} ({}) was not reproduced: {:?}",
+                request.crate_name,
+                request.version,
+                request.env.target,
+                verdict.status
+            );
+            exit(1);
+        }
+
+        let artifact = verdict.find_artifact(&digest).unwrap_or_else(|| {
+            status_err!(
+                "artifact digest {} is not among the reproduced artifacts of {} {} ({})",
+                hex::encode(digest),
+                request.crate_name,
+                request.version,
+                request.env.target
+            );
+            exit(1);
+        });
+
+        // When given the artifact itself, check everything else the
+        // manifest says about it too
+        if let Some(local) = &local_artifact {
+            if local.sha512 != artifact.sha512 || local.size != artifact.size {
+                status_err!(
+                    "artifact matches the SHA-256 digest of {} but not its SHA-512 digest or size",
+                    artifact.path
+                );
+                exit(1);
+            }
+        }
+
+        status_ok!(
+            "Verified",
+            "{} from {} {} ({}, {}) is reproducible",
+            artifact.path,
+            request.crate_name,
+            request.version,
+            request.env.target,
+            request.env.toolchain
+        );
+    }
+}
+
+impl VerifyCmd {
+    /// Print usage and exit
+    fn usage(&self) -> ! {
+        status_err!(
+            "usage: synchronicity verify --proof FILE.synproof --validators validators.toml \
+             [--digest HEX | ARTIFACT]"
+        );
+        exit(1);
+    }
+
+    /// Hash the artifact file given on the command line (if any)
+    fn local_artifact(&self) -> Option<ManifestEntry> {
+        match (&self.digest, self.artifact.as_slice()) {
+            (None, [artifact_path]) => {
+                let name = artifact_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                Some(
+                    ManifestEntry::from_file(artifact_path, name).unwrap_or_else(|e| {
+                        status_err!("couldn't read {}: {}", artifact_path.display(), e);
+                        exit(1);
+                    }),
+                )
+            }
+            (Some(_), []) => None,
+            _ => self.usage(),
+        }
+    }
+
+    /// Parse the SHA-256 digest given with `--digest`
+    fn artifact_digest(&self) -> [u8; 32] {
+        let hex_digest = self.digest.as_ref().unwrap_or_else(|| self.usage());
+        let bytes = hex::decode(hex_digest).unwrap_or_default();
+        let mut digest = [0u8; 32];
+
+        if bytes.len() != digest.len() {
+            status_err!("invalid SHA-256 digest: {:?}", hex_digest);
+            exit(1);
+        }
+
+        digest.copy_from_slice(&bytes);
+        digest
+    }
+}
+
+/// Load the trusted validator set from a `validators.toml` file
+fn load_validator_set(path: &Path) -> ValidatorSet {
+    let peer_info_set = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|toml_string| {
+            toml::from_str::<PeerInfoSet>(&toml_string).map_err(|e| e.to_string())
+        })
+        .unwrap_or_else(|e| {
+            status_err!("couldn't load {}: {}", path.display(), e);
+            exit(1);
+        });
+
+    ValidatorSet::from_peer_infos(&peer_info_set.peers).unwrap_or_else(|e| {
+        status_err!("invalid validator set in {}: {}", path.display(), e);
+        exit(1);
+    })
+}
@@ -1,8 +1,23 @@
 //! Synchronicity Configuration
 
+use crate::{
+    error::{Error, ErrorKind},
+    payload::BuildEnv,
+    prelude::*,
+    registry::{Registry, CRATES_IO_ALIAS},
+    watcher::{DEFAULT_POLL_INTERVAL, DEFAULT_SUBMIT_STAGGER},
+    worker::DEFAULT_RETENTION,
+};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use synchro::config::{NodeConfig, PersistableConfig};
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+use synchro::{
+    config::{keys::ConsensusKeyPair, NodeConfig, PersistableConfig},
+    crypto::ed25519::Ed25519PrivateKey,
+    grpcio::{ChannelBuilder, EnvBuilder},
+    mempool::proto::mempool::MempoolClient,
+    storage_client::StorageReadServiceClient,
+    types::account_address::AccountAddress,
+};
 
 /// Synchronicity Configuration Filename
 pub const CONFIG_FILE: &str = "synchronicity.toml";
@@ -16,11 +31,195 @@ pub struct SynchronicityConfig {
 
     /// Scratch directory
     pub scratch_dir: PathBuf,
+
+    /// Directory the node's embedded LibraDB is stored in (defaults to the
+    /// storage directory in the node config)
+    pub storage_dir: Option<PathBuf>,
+
+    /// Artifact retention and sharing
+    #[serde(default)]
+    pub artifacts: ArtifactsConfig,
+
+    /// crates.io index watcher (disabled if absent)
+    pub watcher: Option<WatcherConfig>,
+
+    /// Alternative registries' index URLs, keyed by alias
+    #[serde(default)]
+    pub registries: BTreeMap<String, String>,
 }
 
 impl SynchronicityConfig {
     /// Load [`NodeConfig`] from the configured location
     pub fn load_node_config(&self) -> NodeConfig {
-        NodeConfig::load_config(&self.node_config)
+        let mut node_config = NodeConfig::load_config(&self.node_config);
+
+        if let Some(storage_dir) = &self.storage_dir {
+            node_config.storage.dir = storage_dir.clone();
+        }
+
+        node_config
+    }
+
+    /// Load the node's consensus private key, which transactions and
+    /// artifact service responses are signed with
+    pub fn load_consensus_key(&self) -> Result<Ed25519PrivateKey, Error> {
+        let node_config = self.load_node_config();
+
+        let keypair_file = self
+            .node_config
+            .parent()
+            .expect("node config has no parent directory")
+            .join(&node_config.consensus.consensus_keypair_file);
+
+        match ConsensusKeyPair::load_config(&keypair_file).take_consensus_private() {
+            Some(key) => Ok(key),
+            None => fail!(
+                ErrorKind::Config,
+                "no consensus private key in {}",
+                keypair_file.display()
+            ),
+        }
+    }
+
+    /// Connect a client to the node's storage service
+    pub fn storage_read_client(&self) -> StorageReadServiceClient {
+        let node_config = self.load_node_config();
+        let env = Arc::new(EnvBuilder::new().name_prefix("grpc-cli-sto-").build());
+
+        StorageReadServiceClient::new(env, &node_config.storage.address, node_config.storage.port)
+    }
+
+    /// Connect a client to the node's mempool service
+    pub fn mempool_client(&self) -> MempoolClient {
+        let node_config = self.load_node_config();
+        let env = Arc::new(EnvBuilder::new().name_prefix("grpc-cli-mem-").build());
+        let channel = ChannelBuilder::new(env).connect(&format!(
+            "{}:{}",
+            node_config.mempool.address, node_config.mempool.mempool_service_port
+        ));
+
+        MempoolClient::new(channel)
+    }
+
+    /// Resolve a registry alias or index URL to the index URL to put in
+    /// build requests (`None` for crates.io)
+    pub fn resolve_registry(&self, alias_or_url: &str) -> Result<Option<String>, Error> {
+        if alias_or_url == CRATES_IO_ALIAS {
+            return Ok(None);
+        }
+
+        let index_url = match self.registries.get(alias_or_url) {
+            Some(index_url) => index_url,
+            None if alias_or_url.contains("://") => alias_or_url,
+            None => fail!(ErrorKind::Config, "unknown registry: {}", alias_or_url),
+        };
+
+        Registry::from_index_url(Some(index_url))?;
+        Ok(Some(index_url.to_owned()))
+    }
+}
+
+/// Artifact retention and sharing configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArtifactsConfig {
+    /// How long to retain build directories in the scratch directory
+    /// (seconds)
+    pub retention_secs: u64,
+
+    /// Address to serve retained artifacts' digests to other builders on.
+    /// If unset, the artifact service is disabled.
+    pub listen_addr: Option<SocketAddr>,
+
+    /// Artifact service addresses of other builders, keyed by their account
+    /// address (hex)
+    pub peers: BTreeMap<String, SocketAddr>,
+}
+
+impl ArtifactsConfig {
+    /// Get the retention period
+    pub fn retention(&self) -> Duration {
+        Duration::from_secs(self.retention_secs)
     }
+
+    /// Get the artifact service address of the given builder (if known)
+    pub fn peer_addr(&self, builder: &AccountAddress) -> Option<SocketAddr> {
+        let builder_hex = builder.to_string();
+
+        self.peers
+            .iter()
+            .find(|(account, _)| {
+                account
+                    .trim_start_matches("0x")
+                    .eq_ignore_ascii_case(&builder_hex)
+            })
+            .map(|(_, addr)| *addr)
+    }
+}
+
+impl Default for ArtifactsConfig {
+    fn default() -> Self {
+        Self {
+            retention_secs: DEFAULT_RETENTION.as_secs(),
+            listen_addr: None,
+            peers: BTreeMap::new(),
+        }
+    }
+}
+
+/// crates.io index watcher configuration
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatcherConfig {
+    /// Local copy of the crates.io index: a git checkout or a plain directory
+    pub index_dir: PathBuf,
+
+    /// Update a git checkout of the index before each scan
+    #[serde(default = "default_fetch")]
+    pub fetch: bool,
+
+    /// Index URL of the registry the index belongs to (crates.io if unset)
+    pub registry: Option<String>,
+
+    /// Names or glob patterns of crates to build
+    pub crates: Vec<String>,
+
+    /// How often to scan the index (seconds)
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+
+    /// How long to wait for each builder ahead of this one to submit a build
+    /// request (seconds)
+    #[serde(default = "default_submit_stagger_secs")]
+    pub submit_stagger_secs: u64,
+
+    /// Environment to request builds in
+    pub env: BuildEnv,
+}
+
+impl WatcherConfig {
+    /// Get the poll interval
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+
+    /// Get the submission stagger interval
+    pub fn submit_stagger(&self) -> Duration {
+        Duration::from_secs(self.submit_stagger_secs)
+    }
+}
+
+/// Update git checkouts of the index by default
+fn default_fetch() -> bool {
+    true
+}
+
+/// Default poll interval for the index watcher
+fn default_poll_interval_secs() -> u64 {
+    DEFAULT_POLL_INTERVAL.as_secs()
+}
+
+/// Default submission stagger interval for the index watcher
+fn default_submit_stagger_secs() -> u64 {
+    DEFAULT_SUBMIT_STAGGER.as_secs()
 }
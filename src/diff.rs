@@ -0,0 +1,277 @@
+//! Divergence diagnosis: finding which artifacts differ between builders who
+//! revealed different manifests, and roughly where within them.
+//!
+//! File-level differences are computed from the manifests published on the
+//! ledger. Byte-level differences are computed from per-block digests of the
+//! artifacts, which builders serve to each other via the artifact service, so
+//! the artifacts themselves never need to be transferred.
+
+use crate::{
+    error::{Error, ErrorKind},
+    manifest::{Manifest, ManifestEntry},
+    prelude::*,
+    state::Digest,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    fs::File,
+    io::{self, Read},
+    ops::Range,
+    path::Path,
+};
+
+/// Size of the blocks artifacts are split into when computing block digests
+pub const BLOCK_SIZE: u64 = 4096;
+
+/// Maximum number of differing regions to display
+pub const MAX_DISPLAYED_REGIONS: usize = 8;
+
+/// Differences between the artifacts in two manifests
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ManifestDiff {
+    /// Changed artifacts, sorted by path
+    pub changes: Vec<FileChange>,
+}
+
+impl ManifestDiff {
+    /// Compute the changes needed to turn `base` into `other`
+    pub fn between(base: &Manifest, other: &Manifest) -> Self {
+        let mut changes = vec![];
+        let mut base_entries = base.entries.iter().peekable();
+        let mut other_entries = other.entries.iter().peekable();
+
+        // Manifests are sorted by path, so they can be merged in one pass
+        loop {
+            let ordering = match (base_entries.peek(), other_entries.peek()) {
+                (Some(a), Some(b)) => a.path.as_bytes().cmp(b.path.as_bytes()),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+
+            match ordering {
+                Ordering::Less => {
+                    changes.push(FileChange::Removed(base_entries.next().unwrap().clone()));
+                }
+                Ordering::Greater => {
+                    changes.push(FileChange::Added(other_entries.next().unwrap().clone()));
+                }
+                Ordering::Equal => {
+                    let a = base_entries.next().unwrap();
+                    let b = other_entries.next().unwrap();
+
+                    if a != b {
+                        changes.push(FileChange::Modified {
+                            base: a.clone(),
+                            other: b.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Self { changes }
+    }
+
+    /// Are the manifests identical?
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Change to a single artifact
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FileChange {
+    /// Artifact only present in the other manifest
+    Added(ManifestEntry),
+
+    /// Artifact only present in the base manifest
+    Removed(ManifestEntry),
+
+    /// Artifact present in both manifests with different contents or mode
+    Modified {
+        /// Entry in the base manifest
+        base: ManifestEntry,
+
+        /// Entry in the other manifest
+        other: ManifestEntry,
+    },
+}
+
+impl FileChange {
+    /// Path of the changed artifact
+    pub fn path(&self) -> &str {
+        match self {
+            FileChange::Added(entry) | FileChange::Removed(entry) => &entry.path,
+            FileChange::Modified { base, .. } => &base.path,
+        }
+    }
+}
+
+impl Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileChange::Added(entry) => write!(f, "+ {} ({} bytes)", entry.path, entry.size),
+            FileChange::Removed(entry) => write!(f, "- {} ({} bytes)", entry.path, entry.size),
+            FileChange::Modified { base, other } => {
+                write!(f, "~ {} ({} -> {} bytes", base.path, base.size, other.size)?;
+
+                if base.mode != other.mode {
+                    write!(f, ", mode {:04o} -> {:04o}", base.mode, other.mode)?;
+                }
+
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// SHA-256 digests of each `BLOCK_SIZE` block of an artifact
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BlockDigests {
+    /// Size of the artifact in bytes
+    pub size: u64,
+
+    /// Digest of each block, in order. The last block may be short.
+    pub blocks: Vec<Digest>,
+}
+
+impl BlockDigests {
+    /// Compute the block digests of the file at the given path
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let mut file = File::open(path).map_err(|e| ErrorKind::Io.context(e))?;
+        let mut block = vec![0u8; BLOCK_SIZE as usize];
+        let mut size = 0;
+        let mut blocks = vec![];
+
+        loop {
+            let nbytes = read_block(&mut file, &mut block).map_err(|e| {
+                ErrorKind::Io.context(format!("error reading {}: {}", path.display(), e))
+            })?;
+
+            if nbytes == 0 {
+                break;
+            }
+
+            let mut digest = [0u8; 32];
+            digest.copy_from_slice(&Sha256::digest(&block[..nbytes]));
+            blocks.push(digest);
+            size += nbytes as u64;
+        }
+
+        Ok(Self { size, blocks })
+    }
+
+    /// Check the number of blocks is consistent with the size
+    pub fn validate(&self) -> Result<(), Error> {
+        // Rounding up by adding `BLOCK_SIZE - 1` would overflow for huge sizes
+        let expected_blocks = self.size / BLOCK_SIZE + (self.size % BLOCK_SIZE != 0) as u64;
+
+        if self.blocks.len() as u64 != expected_blocks {
+            fail!(
+                ErrorKind::Payload,
+                "expected {} block digest(s) for {} bytes, got {}",
+                expected_blocks,
+                self.size,
+                self.blocks.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Compare these block digests against another artifact's
+    pub fn compare(&self, other: &BlockDigests) -> BinaryDiff {
+        let mut regions: Vec<Range<u64>> = vec![];
+        let max_size = self.size.max(other.size);
+        let nblocks = self.blocks.len().max(other.blocks.len());
+
+        for i in 0..nblocks {
+            if self.blocks.get(i).is_some() && self.blocks.get(i) == other.blocks.get(i) {
+                continue;
+            }
+
+            let start = i as u64 * BLOCK_SIZE;
+            let end = (start + BLOCK_SIZE).min(max_size);
+
+            // Coalesce adjacent differing blocks into a single region
+            match regions.last_mut() {
+                Some(region) if region.end == start => region.end = end,
+                _ => regions.push(start..end),
+            }
+        }
+
+        BinaryDiff {
+            base_size: self.size,
+            other_size: other.size,
+            regions,
+        }
+    }
+}
+
+/// Summary of the byte-level differences between two artifacts, at block
+/// granularity
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BinaryDiff {
+    /// Size of the base artifact in bytes
+    pub base_size: u64,
+
+    /// Size of the other artifact in bytes
+    pub other_size: u64,
+
+    /// Byte ranges containing differences
+    pub regions: Vec<Range<u64>>,
+}
+
+impl BinaryDiff {
+    /// Total number of bytes in differing regions
+    pub fn differing_bytes(&self) -> u64 {
+        self.regions
+            .iter()
+            .map(|region| region.end - region.start)
+            .sum()
+    }
+}
+
+impl Display for BinaryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max_size = self.base_size.max(self.other_size);
+
+        write!(
+            f,
+            "{} of {} bytes differ in {} region(s)",
+            self.differing_bytes(),
+            max_size,
+            self.regions.len()
+        )?;
+
+        for region in self.regions.iter().take(MAX_DISPLAYED_REGIONS) {
+            write!(f, " [{:#x}..{:#x})", region.start, region.end)?;
+        }
+
+        if self.regions.len() > MAX_DISPLAYED_REGIONS {
+            write!(f, " ...")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fill as much of a block as possible, returning the number of bytes read
+fn read_block(file: &mut File, block: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+
+    while filled < block.len() {
+        match file.read(&mut block[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
@@ -18,6 +18,34 @@ pub enum ErrorKind {
     /// Input/output error
     #[error("I/O error")]
     Io,
+
+    /// Malformed transaction payload
+    #[error("payload error")]
+    Payload,
+
+    /// Error generating or verifying a proof
+    #[error("proof error")]
+    Proof,
+
+    /// Error building a crate
+    #[error("build error")]
+    Build,
+
+    /// Error communicating with a node
+    #[error("client error")]
+    Client,
+
+    /// Error fetching from a crate registry
+    #[error("registry error")]
+    Registry,
+
+    /// Downloaded sources don't match the registry's checksum
+    #[error("checksum mismatch")]
+    Checksum,
+
+    /// Error launching the node
+    #[error("launch error")]
+    Launch,
 }
 
 impl ErrorKind {
@@ -1,42 +1,544 @@
 //! Synchronicity state machine executor
 
+use crate::{
+    log,
+    payload::{Attestation, BuildRequest, Commit, Payload, Reveal},
+    state::{self, Build, BuildId, Event, Params, Phase, StateCache},
+    verdict::Verdict,
+};
+use std::collections::BTreeSet;
 use synchro::{
     config::VMConfig,
     error::Error,
     state_view::StateView,
     transaction::{SignedTransaction, Status, Transaction, TransactionOutput},
+    types::{
+        account_address::AccountAddress, block_metadata::BlockMetadata,
+        contract_event::ContractEvent, event::EventKey, transaction::TransactionStatus,
+        vm_error::StatusCode, write_set::WriteSet,
+    },
     vm_runtime::{VMExecutor, VMVerifier},
 };
 
+/// Salt used when computing the key of the Synchronicity event stream
+pub const EVENT_KEY_SALT: u64 = 0;
+
+/// Sub-status codes for transactions which were kept on the ledger but
+/// rejected by the state machine (reported with `StatusCode::ABORTED`)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Rejection {
+    /// A build with the same ID has already been requested
+    DuplicateBuild = 1,
+
+    /// The referenced build does not exist
+    UnknownBuild = 2,
+
+    /// The builder has already committed to this build
+    DuplicateCommit = 3,
+
+    /// The builder never committed to this build
+    MissingCommit = 4,
+
+    /// The builder has already revealed for this build
+    DuplicateReveal = 5,
+
+    /// The reveal doesn't open the builder's commitment
+    CommitmentMismatch = 6,
+
+    /// The builder never revealed a result for this build
+    MissingReveal = 7,
+
+    /// The attested artifact root doesn't match the builder's reveal
+    AttestationMismatch = 8,
+
+    /// The builder has already attested to this build
+    DuplicateAttestation = 9,
+
+    /// The sender isn't one of the group's builders
+    NotABuilder = 10,
+
+    /// The build isn't accepting commitments (deadline passed)
+    CommitPhaseClosed = 11,
+
+    /// The build isn't accepting reveals (commit phase still running,
+    /// or deadline passed)
+    RevealPhaseClosed = 12,
+
+    /// The build hasn't been finalized yet
+    NotFinalized = 13,
+}
+
+impl Rejection {
+    /// Get the `Status` for this rejection
+    pub fn status(self) -> Status {
+        Status::new(StatusCode::ABORTED).with_sub_status(self as u64)
+    }
+}
+
+/// Outcome of applying a payload: events to emit, or the reason for rejection
+type Outcome = Result<Vec<Event>, Rejection>;
+
 /// State machine executor used by Synchronicity
+#[derive(Clone, Debug)]
 pub struct SynchronicityExecutor {}
 
 impl SynchronicityExecutor {
-    /// Create a new SynchronicityExecutor (stub!)
+    /// Create a new SynchronicityExecutor
     #[allow(clippy::new_without_default)] // sate clippy, for now
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Execute a single transaction against the given state
+    fn execute_transaction(
+        state: &mut StateCache<'_>,
+        transaction: Transaction,
+    ) -> Result<TransactionOutput, Error> {
+        match transaction {
+            Transaction::UserTransaction(txn) => Self::execute_user_transaction(state, txn),
+            Transaction::WriteSet(write_set) => Self::execute_write_set(state, write_set),
+            Transaction::BlockMetadata(metadata) => Self::execute_block_metadata(state, metadata),
+        }
+    }
+
+    /// Record the block timestamp and advance the phases of pending builds
+    fn execute_block_metadata(
+        state: &mut StateCache<'_>,
+        metadata: BlockMetadata,
+    ) -> Result<TransactionOutput, Error> {
+        let now = metadata.timestamp_usec();
+
+        // Ignore timestamps which would move the clock backwards
+        if now <= current_time(state)? {
+            return Ok(keep(WriteSet::default(), vec![]));
+        }
+
+        state.put(state::timestamp_path(), &now)?;
+
+        let params = current_params(state)?;
+        let pending_builds = state
+            .get::<BTreeSet<BuildId>>(&state::pending_builds_path())?
+            .unwrap_or_default();
+
+        let mut events = vec![];
+        let mut missing_builds = vec![];
+
+        for build_id in pending_builds {
+            // A missing record is a bug, but halting every validator over it
+            // would be worse: drop it from the pending set instead
+            let mut build = match state.get::<Build>(&state::build_path(&build_id))? {
+                Some(build) => build,
+                None => {
+                    missing_builds.push(build_id);
+                    continue;
+                }
+            };
+
+            update_build(state, build_id, &mut build, now, &params, &mut events)?;
+        }
+
+        if !missing_builds.is_empty() {
+            let pending_builds_path = state::pending_builds_path();
+            let mut pending_builds = state
+                .get::<BTreeSet<BuildId>>(&pending_builds_path)?
+                .unwrap_or_default();
+
+            for build_id in &missing_builds {
+                pending_builds.remove(build_id);
+            }
+
+            state.put(pending_builds_path, &pending_builds)?;
+        }
+
+        let events = emit_events(state, events)?;
+        Ok(keep(state.commit(), events))
+    }
+
+    /// Apply a genesis write set
+    fn execute_write_set(
+        state: &mut StateCache<'_>,
+        write_set: WriteSet,
+    ) -> Result<TransactionOutput, Error> {
+        if !state.is_genesis() {
+            return Ok(discard(StatusCode::REJECTED_WRITE_SET));
+        }
+
+        Ok(keep(write_set, vec![]))
+    }
+
+    /// Execute a transaction submitted by a user
+    fn execute_user_transaction(
+        state: &mut StateCache<'_>,
+        txn: SignedTransaction,
+    ) -> Result<TransactionOutput, Error> {
+        let sender = txn.sender();
+        let txn = match txn.check_signature() {
+            Ok(checked) => checked.into_inner(),
+            Err(_) => return Ok(discard(StatusCode::INVALID_SIGNATURE)),
+        };
+
+        if !is_signed_by_sender(&txn) {
+            return Ok(discard(StatusCode::INVALID_AUTH_KEY));
+        }
+
+        let sequence_number_path = state::sequence_number_path(sender);
+        let expected_sequence_number = state.get::<u64>(&sequence_number_path)?.unwrap_or(0);
+
+        if txn.sequence_number() < expected_sequence_number {
+            return Ok(discard(StatusCode::SEQUENCE_NUMBER_TOO_OLD));
+        } else if txn.sequence_number() > expected_sequence_number {
+            return Ok(discard(StatusCode::SEQUENCE_NUMBER_TOO_NEW));
+        }
+
+        let payload = match decode_payload(&txn) {
+            Ok(payload) => payload,
+            Err(status_code) => return Ok(discard(status_code)),
+        };
+
+        let result = match payload {
+            Payload::BuildRequest(request) => Self::apply_build_request(state, sender, request),
+            Payload::Commit(commit) => Self::apply_commit(state, sender, commit),
+            Payload::Reveal(reveal) => Self::apply_reveal(state, sender, reveal),
+            Payload::Attestation(attestation) => {
+                Self::apply_attestation(state, sender, attestation)
+            }
+        }?;
+
+        let (status, events) = match result {
+            Ok(events) => (Status::new(StatusCode::EXECUTED), events),
+            Err(rejection) => {
+                // Rejected transactions are still kept so they can't be replayed
+                state.discard();
+                (rejection.status(), vec![])
+            }
+        };
+
+        state.put(sequence_number_path, &(expected_sequence_number + 1))?;
+        let events = emit_events(state, events)?;
+        let write_set = state.commit();
+
+        Ok(TransactionOutput::new(
+            write_set,
+            events,
+            0,
+            TransactionStatus::Keep(status),
+        ))
+    }
+
+    /// Record a new build request
+    fn apply_build_request(
+        state: &mut StateCache<'_>,
+        requester: AccountAddress,
+        request: BuildRequest,
+    ) -> Result<Outcome, Error> {
+        let build_id = request.id();
+        let build_path = state::build_path(&build_id);
+
+        if state.get_raw(&build_path)?.is_some() {
+            return Ok(Err(Rejection::DuplicateBuild));
+        }
+
+        let now = current_time(state)?;
+        let params = current_params(state)?;
+        state.put(build_path, &Build::new(request, requester, now, &params))?;
+
+        let pending_builds_path = state::pending_builds_path();
+        let mut pending_builds = state
+            .get::<BTreeSet<BuildId>>(&pending_builds_path)?
+            .unwrap_or_default();
+
+        pending_builds.insert(build_id);
+        state.put(pending_builds_path, &pending_builds)?;
+
+        Ok(Ok(vec![Event::BuildRequested(build_id)]))
+    }
+
+    /// Record a builder's commitment to a build result
+    fn apply_commit(
+        state: &mut StateCache<'_>,
+        builder: AccountAddress,
+        commit: Commit,
+    ) -> Result<Outcome, Error> {
+        let build_path = state::build_path(&commit.build_id);
+
+        let mut build = match state.get::<Build>(&build_path)? {
+            Some(build) => build,
+            None => return Ok(Err(Rejection::UnknownBuild)),
+        };
+
+        let now = current_time(state)?;
+        let params = current_params(state)?;
+
+        if !params.is_builder(&builder) {
+            return Ok(Err(Rejection::NotABuilder));
+        }
+
+        if !build.is_committing(now) {
+            return Ok(Err(Rejection::CommitPhaseClosed));
+        }
+
+        if build.commits.contains_key(&builder) {
+            return Ok(Err(Rejection::DuplicateCommit));
+        }
+
+        build.commits.insert(builder, commit.commitment);
+        build.sources.insert(builder, commit.source_digest);
+
+        let mut events = vec![Event::Committed(commit.build_id, builder)];
+        update_build(
+            state,
+            commit.build_id,
+            &mut build,
+            now,
+            &params,
+            &mut events,
+        )?;
+        Ok(Ok(events))
+    }
+
+    /// Record a builder's reveal of a build result
+    fn apply_reveal(
+        state: &mut StateCache<'_>,
+        builder: AccountAddress,
+        reveal: Reveal,
+    ) -> Result<Outcome, Error> {
+        let build_path = state::build_path(&reveal.build_id);
+
+        let mut build = match state.get::<Build>(&build_path)? {
+            Some(build) => build,
+            None => return Ok(Err(Rejection::UnknownBuild)),
+        };
+
+        let now = current_time(state)?;
+        let params = current_params(state)?;
+
+        if !params.is_builder(&builder) {
+            return Ok(Err(Rejection::NotABuilder));
+        }
+
+        if !build.is_revealing(now) {
+            return Ok(Err(Rejection::RevealPhaseClosed));
+        }
+
+        if build.reveals.contains_key(&builder) {
+            return Ok(Err(Rejection::DuplicateReveal));
+        }
+
+        let source_digest = match build.sources.get(&builder) {
+            Some(source_digest) => *source_digest,
+            None => return Ok(Err(Rejection::MissingCommit)),
+        };
+
+        match build.commits.get(&builder) {
+            Some(commitment) if *commitment == reveal.commitment(&builder, &source_digest) => (),
+            Some(_) => return Ok(Err(Rejection::CommitmentMismatch)),
+            None => return Ok(Err(Rejection::MissingCommit)),
+        }
+
+        let artifact_root = reveal.artifact_root();
+        build.reveals.insert(builder, artifact_root);
+        build
+            .manifests
+            .entry(artifact_root)
+            .or_insert_with(|| reveal.manifest.clone());
+
+        let mut events = vec![Event::Revealed(reveal.build_id, builder)];
+        update_build(
+            state,
+            reveal.build_id,
+            &mut build,
+            now,
+            &params,
+            &mut events,
+        )?;
+        Ok(Ok(events))
+    }
+
+    /// Record a builder's attestation to a build result
+    fn apply_attestation(
+        state: &mut StateCache<'_>,
+        builder: AccountAddress,
+        attestation: Attestation,
+    ) -> Result<Outcome, Error> {
+        let build_path = state::build_path(&attestation.build_id);
+
+        let mut build = match state.get::<Build>(&build_path)? {
+            Some(build) => build,
+            None => return Ok(Err(Rejection::UnknownBuild)),
+        };
+
+        if build.phase != Phase::Finalized {
+            return Ok(Err(Rejection::NotFinalized));
+        }
+
+        match build.reveals.get(&builder) {
+            Some(artifact_root) if *artifact_root == attestation.artifact_root => (),
+            Some(_) => return Ok(Err(Rejection::AttestationMismatch)),
+            None => return Ok(Err(Rejection::MissingReveal)),
+        }
+
+        if !build.attestations.insert(builder) {
+            return Ok(Err(Rejection::DuplicateAttestation));
+        }
+
+        state.put(build_path, &build)?;
+        Ok(Ok(vec![Event::Attested(attestation.build_id, builder)]))
+    }
 }
 
 impl VMExecutor for SynchronicityExecutor {
     fn execute_block(
-        _transactions: Vec<Transaction>,
+        transactions: Vec<Transaction>,
         _config: &VMConfig,
-        _state_view: &dyn StateView,
+        state_view: &dyn StateView,
     ) -> Result<Vec<TransactionOutput>, Error> {
-        // TODO(tarcieri): implement this!
-        unimplemented!();
+        let mut state = StateCache::new(state_view);
+
+        transactions
+            .into_iter()
+            .map(|transaction| Self::execute_transaction(&mut state, transaction))
+            .collect()
     }
 }
 
 impl VMVerifier for SynchronicityExecutor {
+    /// Check a transaction is admissible to the mempool, returning the
+    /// `Status` it should be rejected with if not
     fn validate_transaction(
         &self,
-        _transaction: SignedTransaction,
-        _state_view: &dyn StateView,
+        transaction: SignedTransaction,
+        state_view: &dyn StateView,
     ) -> Option<Status> {
-        // TODO(tarcieri): implement this!
-        unimplemented!();
+        let state = StateCache::new(state_view);
+
+        let txn = match transaction.check_signature() {
+            Ok(checked) => checked.into_inner(),
+            Err(_) => return Some(Status::new(StatusCode::INVALID_SIGNATURE)),
+        };
+
+        if !is_signed_by_sender(&txn) {
+            return Some(Status::new(StatusCode::INVALID_AUTH_KEY));
+        }
+
+        let sequence_number_path = state::sequence_number_path(txn.sender());
+
+        // Sequence numbers which are too new are accepted: the mempool holds
+        // onto them until the preceding transactions arrive
+        match state.get::<u64>(&sequence_number_path) {
+            Ok(expected) if txn.sequence_number() < expected.unwrap_or(0) => {
+                return Some(Status::new(StatusCode::SEQUENCE_NUMBER_TOO_OLD))
+            }
+            Ok(_) => (),
+            Err(_) => return Some(Status::new(StatusCode::UNKNOWN_VALIDATION_STATUS)),
+        }
+
+        decode_payload(&txn).err().map(Status::new)
     }
 }
+
+/// Is the transaction signed with the key its sender's address is derived
+/// from? A valid signature alone only proves the signer holds the key
+/// embedded in the transaction, which could be anyone's.
+fn is_signed_by_sender(txn: &SignedTransaction) -> bool {
+    AccountAddress::from_public_key(&txn.public_key()) == txn.sender()
+}
+
+/// Decode and check the payload of a transaction
+fn decode_payload(txn: &SignedTransaction) -> Result<Payload, StatusCode> {
+    let payload = Payload::from_transaction(txn).map_err(|_| StatusCode::MALFORMED)?;
+    payload.validate().map_err(|_| StatusCode::MALFORMED)?;
+    Ok(payload)
+}
+
+/// Get the timestamp of the latest block
+fn current_time(state: &StateCache<'_>) -> Result<u64, Error> {
+    Ok(state.get(&state::timestamp_path())?.unwrap_or(0))
+}
+
+/// Get the group's protocol parameters
+fn current_params(state: &StateCache<'_>) -> Result<Params, Error> {
+    Ok(state.get(&state::params_path())?.unwrap_or_default())
+}
+
+/// Advance a build's phase and write it back to the state.
+///
+/// Once the build reaches a terminal phase, its verdict is recorded and
+/// appended to the reproducibility log, and it's removed from the pending set.
+fn update_build(
+    state: &mut StateCache<'_>,
+    build_id: BuildId,
+    build: &mut Build,
+    now: u64,
+    params: &Params,
+    events: &mut Vec<Event>,
+) -> Result<(), Error> {
+    if build.advance(now, params) {
+        events.push(Event::PhaseChanged(build_id, build.phase));
+
+        if build.phase.is_terminal() {
+            let log_index = log::accumulator(state)?.size();
+            let verdict = Verdict::decide(build_id, build, now, params, log_index);
+            log::append(state, &verdict.to_log_leaf())?;
+
+            state.put(state::request_verdict_path(&build.request), &verdict)?;
+            events.push(Event::VerdictReached(build_id));
+
+            let pending_builds_path = state::pending_builds_path();
+            let mut pending_builds = state
+                .get::<BTreeSet<BuildId>>(&pending_builds_path)?
+                .unwrap_or_default();
+
+            pending_builds.remove(&build_id);
+            state.put(pending_builds_path, &pending_builds)?;
+        }
+    }
+
+    state.put(state::build_path(&build_id), build)
+}
+
+/// Serialize events and assign them sequence numbers
+fn emit_events(
+    state: &mut StateCache<'_>,
+    events: Vec<Event>,
+) -> Result<Vec<ContractEvent>, Error> {
+    let event_count_path = state::event_count_path();
+    let mut event_count = state.get::<u64>(&event_count_path)?.unwrap_or(0);
+    let event_key = EventKey::new_from_address(&state::state_account(), EVENT_KEY_SALT);
+    let mut contract_events = Vec::with_capacity(events.len());
+
+    for event in events {
+        contract_events.push(ContractEvent::new(
+            event_key,
+            event_count,
+            bincode::serialize(&event)?,
+        ));
+
+        event_count += 1;
+    }
+
+    if !contract_events.is_empty() {
+        state.put(event_count_path, &event_count)?;
+    }
+
+    Ok(contract_events)
+}
+
+/// Output for a transaction which is kept on the ledger
+fn keep(write_set: WriteSet, events: Vec<ContractEvent>) -> TransactionOutput {
+    TransactionOutput::new(
+        write_set,
+        events,
+        0,
+        TransactionStatus::Keep(Status::new(StatusCode::EXECUTED)),
+    )
+}
+
+/// Output for a transaction which is discarded
+fn discard(status_code: StatusCode) -> TransactionOutput {
+    TransactionOutput::new(
+        WriteSet::default(),
+        vec![],
+        0,
+        TransactionStatus::Discard(Status::new(status_code)),
+    )
+}
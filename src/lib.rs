@@ -13,9 +13,10 @@
 //! ## About
 //!
 //! **Synchronicity** is a distributed build system for Rust crates which have
-//! been published to [crates.io]. It builds crates reproducibly inside of Docker
-//! containers managed using [Rustwide], the core library behind tools like
-//! [Crater] and [docs.rs].
+//! been published to [crates.io] (or to an alternative registry with a sparse or
+//! local index). It builds crates reproducibly inside of Docker containers
+//! managed using [Rustwide], the core library behind tools like [Crater] and
+//! [docs.rs].
 //!
 //! Builders running **Synchronicity** also run a BFT consensus algorithm between
 //! each other (as part of a closed, "permissioned" group), and in doing so come to
@@ -47,9 +48,22 @@
 #![warn(rust_2018_idioms, unused_lifetimes, unused_qualifications)]
 
 pub mod application;
+pub mod artifact_service;
+pub mod backend;
+pub mod client;
 pub mod commands;
 pub mod config;
+pub mod diff;
 pub mod error;
 pub mod executor;
+pub mod log;
+pub mod manifest;
+pub mod participant;
+pub mod payload;
 pub mod prelude;
+pub mod registry;
+pub mod state;
+pub mod verdict;
 pub mod verifier;
+pub mod watcher;
+pub mod worker;
@@ -0,0 +1,216 @@
+//! Reproducibility log: finalized verdicts are appended to an append-only
+//! Merkle log kept in ledger state, from which proofs of inclusion (against
+//! ledger infos signed by a quorum of validators) and proofs of consistency
+//! between log sizes can be generated.
+
+use crate::{
+    error::{Error, ErrorKind},
+    prelude::*,
+    state::{self, StateCache},
+};
+use std::{collections::BTreeMap, convert::TryFrom};
+use synchro::{
+    log::{self, Accumulator, Checkpoint, ConsistencyProof, LedgerProof, MerkleLog},
+    proof::ValidatorSet,
+    storage_client::StorageRead,
+    types::{
+        account_state_blob::AccountStateWithProof,
+        get_with_proof::{RequestItem, ResponseItem},
+        ledger_info::LedgerInfoWithSignatures,
+    },
+};
+
+/// Get the log's accumulator from the state
+pub fn accumulator(state: &StateCache<'_>) -> Result<Accumulator, synchro::error::Error> {
+    Ok(state.get(&state::log_path())?.unwrap_or_default())
+}
+
+/// Append a leaf to the log, returning its index
+pub fn append(state: &mut StateCache<'_>, leaf: &[u8]) -> Result<u64, synchro::error::Error> {
+    let mut accumulator = accumulator(state)?;
+    let index = accumulator.append(log::leaf_hash(leaf));
+
+    state.put(state::log_leaf_path(index), &leaf)?;
+    state.put(state::log_path(), &accumulator)?;
+    Ok(index)
+}
+
+/// Snapshot of the reproducibility log as of a quorum-signed ledger info
+pub struct LogSnapshot {
+    /// Leaves of the log
+    leaves: Vec<Vec<u8>>,
+
+    /// Merkle log computed from the leaves
+    merkle_log: MerkleLog,
+
+    /// State of the account holding the log's accumulator, with proof
+    account_state: AccountStateWithProof,
+
+    /// Ledger info the snapshot was taken at
+    ledger_info: LedgerInfoWithSignatures,
+}
+
+impl LogSnapshot {
+    /// Fetch the log as of the latest ledger info from storage
+    pub fn fetch(storage: &dyn StorageRead) -> Result<Self, Error> {
+        // The accumulator is kept under its own account, so proofs only carry
+        // the (small) account holding it
+        let request = RequestItem::GetAccountState {
+            address: state::log_account(),
+        };
+
+        let (response_items, ledger_info, ..) =
+            storage
+                .update_to_latest_ledger(0, vec![request])
+                .map_err(|e| ErrorKind::Proof.context(e.to_string()))?;
+
+        let account_state = account_state_response(response_items.into_iter().next())?;
+
+        let accumulator: Accumulator = match account_values(&account_state)?.get(state::LOG_PATH) {
+            Some(bytes) => bincode::deserialize(bytes).map_err(|e| ErrorKind::Proof.context(e))?,
+            None => Accumulator::new(),
+        };
+
+        let mut leaves = Vec::with_capacity(accumulator.size() as usize);
+        let mut merkle_log = MerkleLog::new();
+
+        // Each leaf is kept under its own account. Leaves are never
+        // rewritten, so reading them from a later ledger state than the
+        // accumulator yields the same leaves.
+        if accumulator.size() > 0 {
+            let requests = (0..accumulator.size())
+                .map(|index| RequestItem::GetAccountState {
+                    address: state::log_leaf_path(index).address,
+                })
+                .collect();
+
+            let (response_items, ..) = storage
+                .update_to_latest_ledger(0, requests)
+                .map_err(|e| ErrorKind::Proof.context(e.to_string()))?;
+
+            let mut response_items = response_items.into_iter();
+
+            for index in 0..accumulator.size() {
+                let leaf_state = account_state_response(response_items.next())?;
+
+                let leaf: Vec<u8> =
+                    match account_values(&leaf_state)?.get(&state::log_leaf_path(index).path) {
+                        Some(bytes) => {
+                            bincode::deserialize(bytes).map_err(|e| ErrorKind::Proof.context(e))?
+                        }
+                        None => fail!(ErrorKind::Proof, "log leaf {} missing from state", index),
+                    };
+
+                merkle_log.append(log::leaf_hash(&leaf));
+                leaves.push(leaf);
+            }
+        }
+
+        if merkle_log.root() != accumulator.root() {
+            fail!(ErrorKind::Proof, "log leaves inconsistent with accumulator");
+        }
+
+        Ok(Self {
+            leaves,
+            merkle_log,
+            account_state,
+            ledger_info,
+        })
+    }
+
+    /// Get a checkpoint of the log's current size and root
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.merkle_log
+            .checkpoint_at(self.merkle_log.size())
+            .expect("checkpoint at current size")
+    }
+
+    /// Get a checkpoint of the log from the ledger state signed by a quorum
+    /// of the given validator set, after checking the snapshot's leaves
+    /// match it
+    pub fn signed_checkpoint(&self, validator_set: &ValidatorSet) -> Result<Checkpoint, Error> {
+        let accumulator = log::ledger::verify_accumulator(
+            &self.ledger_info,
+            &self.account_state,
+            &validator_set.verifier(),
+            state::log_account(),
+            state::LOG_PATH,
+        )
+        .map_err(|e| ErrorKind::Proof.context(e))?;
+
+        if accumulator.checkpoint() != self.checkpoint() {
+            fail!(
+                ErrorKind::Proof,
+                "log leaves inconsistent with signed ledger state"
+            );
+        }
+
+        Ok(accumulator.checkpoint())
+    }
+
+    /// Get the leaf at the given index
+    pub fn leaf(&self, leaf_index: u64) -> Option<&[u8]> {
+        self.leaves.get(leaf_index as usize).map(AsRef::as_ref)
+    }
+
+    /// Get the leaf at the given index, along with a proof of its inclusion
+    /// in the log as of the snapshot's ledger info
+    pub fn inclusion_proof(&self, leaf_index: u64) -> Result<(Vec<u8>, LedgerProof), Error> {
+        let size = self.merkle_log.size();
+
+        let inclusion = match self.merkle_log.inclusion_proof(leaf_index, size) {
+            Some(proof) => proof,
+            None => fail!(
+                ErrorKind::Proof,
+                "leaf index {} out of range (log size: {})",
+                leaf_index,
+                size
+            ),
+        };
+
+        let proof = LedgerProof {
+            inclusion,
+            account_state: self.account_state.clone(),
+            ledger_info: self.ledger_info.clone(),
+        };
+
+        Ok((self.leaves[leaf_index as usize].clone(), proof))
+    }
+
+    /// Generate a proof that the current log is an append-only extension of
+    /// the log as of `old_size`
+    pub fn consistency_proof(&self, old_size: u64) -> Result<ConsistencyProof, Error> {
+        let size = self.merkle_log.size();
+
+        match self.merkle_log.consistency_proof(old_size, size) {
+            Some(proof) => Ok(proof),
+            None => fail!(
+                ErrorKind::Proof,
+                "old size {} exceeds log size {}",
+                old_size,
+                size
+            ),
+        }
+    }
+}
+
+/// Get the account state (with proof) from a storage response
+fn account_state_response(item: Option<ResponseItem>) -> Result<AccountStateWithProof, Error> {
+    match item {
+        Some(ResponseItem::GetAccountState {
+            account_state_with_proof,
+        }) => Ok(account_state_with_proof),
+        _ => fail!(ErrorKind::Proof, "unexpected response from storage"),
+    }
+}
+
+/// Decode the values stored under an account (if it exists)
+fn account_values(
+    account_state: &AccountStateWithProof,
+) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, Error> {
+    match &account_state.blob {
+        Some(blob) => Ok(BTreeMap::<Vec<u8>, Vec<u8>>::try_from(blob)
+            .map_err(|e| ErrorKind::Proof.context(e.to_string()))?),
+        None => Ok(BTreeMap::new()),
+    }
+}
@@ -0,0 +1,357 @@
+//! Artifact manifests: canonical descriptions of the outputs of a build,
+//! including the `.crate` tarball which was built.
+//!
+//! The manifest root, i.e. the value builders commit to and reveal, is the
+//! SHA-256 digest of the manifest's encoding. The encoding is stable so third
+//! parties can recompute the root from a set of artifacts using only common
+//! command line tools.
+//!
+//! ## Encoding
+//!
+//! A manifest is encoded as one line per artifact, each terminated by `\n`:
+//!
+//! ```text
+//! <sha256> <sha512> <mode> <size> <path>
+//! ```
+//!
+//! - `sha256` and `sha512`: lowercase hex digests of the artifact's contents
+//! - `mode`: `0755` if the artifact is executable by anyone, otherwise `0644`
+//!   (other permission bits depend on the builder's umask and are ignored)
+//! - `size`: size of the artifact in bytes, in decimal without leading zeroes
+//! - `path`: path of the artifact relative to the artifacts directory, with
+//!   `/` as the separator. Paths are UTF-8, must not be empty, must not
+//!   contain `.` or `..` components or control characters, and must not
+//!   start or end with `/`
+//!
+//! Lines are sorted by the bytes of `path`, and each path appears only once.
+//! A manifest must contain at least one artifact.
+
+use crate::{
+    error::{Error, ErrorKind},
+    prelude::*,
+    state::Digest,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest as _, Sha256, Sha512};
+use std::{
+    fmt::{self, Display},
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// Mode of executable artifacts
+pub const EXECUTABLE_MODE: u32 = 0o755;
+
+/// Mode of non-executable artifacts
+pub const FILE_MODE: u32 = 0o644;
+
+/// SHA-512 digest
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct Sha512Digest(pub [u8; 64]);
+
+impl fmt::Debug for Sha512Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sha512Digest({})", hex::encode(&self.0[..]))
+    }
+}
+
+impl Serialize for Sha512Digest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Sha512Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+
+        if bytes.len() != 64 {
+            return Err(de::Error::invalid_length(bytes.len(), &"64 bytes"));
+        }
+
+        let mut digest = [0u8; 64];
+        digest.copy_from_slice(&bytes);
+        Ok(Sha512Digest(digest))
+    }
+}
+
+/// Description of a single build artifact
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the artifacts directory (e.g. `libfoo.rlib`)
+    pub path: String,
+
+    /// Size in bytes
+    pub size: u64,
+
+    /// Normalized mode (`0755` or `0644`)
+    pub mode: u32,
+
+    /// SHA-256 digest of the artifact's contents
+    pub sha256: Digest,
+
+    /// SHA-512 digest of the artifact's contents
+    pub sha512: Sha512Digest,
+}
+
+impl ManifestEntry {
+    /// Hash the file at the given path, recording it under `relative_path`
+    pub fn from_file(file_path: &Path, relative_path: impl Into<String>) -> Result<Self, Error> {
+        let metadata = fs::metadata(file_path).map_err(|e| ErrorKind::Io.context(e))?;
+        let mut file = File::open(file_path).map_err(|e| ErrorKind::Io.context(e))?;
+        let mut hasher = Hasher::default();
+
+        io::copy(&mut file, &mut hasher).map_err(|e| {
+            ErrorKind::Io.context(format!("error reading {}: {}", file_path.display(), e))
+        })?;
+
+        let mut entry = Self {
+            path: relative_path.into(),
+            size: metadata.len(),
+            mode: if is_executable(&metadata) {
+                EXECUTABLE_MODE
+            } else {
+                FILE_MODE
+            },
+            sha256: [0u8; 32],
+            sha512: Sha512Digest([0u8; 64]),
+        };
+
+        entry.sha256.copy_from_slice(&hasher.sha256.result());
+        entry.sha512.0.copy_from_slice(&hasher.sha512.result());
+        Ok(entry)
+    }
+
+    /// Check this entry is well-formed
+    pub fn validate(&self) -> Result<(), Error> {
+        let valid_path = !self.path.is_empty()
+            && !self.path.chars().any(char::is_control)
+            && self
+                .path
+                .split('/')
+                .all(|component| !component.is_empty() && component != "." && component != "..");
+
+        if !valid_path {
+            fail!(ErrorKind::Payload, "invalid artifact path: {:?}", self.path);
+        }
+
+        if self.mode != EXECUTABLE_MODE && self.mode != FILE_MODE {
+            fail!(
+                ErrorKind::Payload,
+                "invalid mode for {:?}: {:o}",
+                self.path,
+                self.mode
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for ManifestEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} {:04o} {} {}",
+            hex::encode(self.sha256),
+            hex::encode(&self.sha512.0[..]),
+            self.mode,
+            self.size,
+            self.path
+        )
+    }
+}
+
+/// Manifest of the artifacts produced by a build
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Artifacts, sorted by path
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Create a manifest of every file in the given directory (recursively)
+    pub fn from_dir(dir: &Path) -> Result<Self, Error> {
+        let mut entries = vec![];
+        add_dir_entries(dir, "", &mut entries)?;
+        entries.sort_by(|a, b| a.path.as_bytes().cmp(b.path.as_bytes()));
+
+        let manifest = Self { entries };
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Check this manifest is well-formed and canonical
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.entries.is_empty() {
+            fail!(ErrorKind::Payload, "manifest contains no artifacts");
+        }
+
+        for entry in &self.entries {
+            entry.validate()?;
+        }
+
+        // Paths must be strictly increasing, i.e. sorted with no duplicates
+        for pair in self.entries.windows(2) {
+            if pair[0].path.as_bytes() >= pair[1].path.as_bytes() {
+                fail!(
+                    ErrorKind::Payload,
+                    "manifest not sorted/unique: {:?}",
+                    pair[1].path
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compute the manifest root: the SHA-256 digest of its encoding
+    pub fn root(&self) -> Digest {
+        let mut root = [0u8; 32];
+        root.copy_from_slice(&Sha256::digest(self.to_string().as_bytes()));
+        root
+    }
+
+    /// Find the artifact with the given SHA-256 digest (if any)
+    pub fn find(&self, sha256: &Digest) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|entry| entry.sha256 == *sha256)
+    }
+}
+
+impl Display for Manifest {
+    /// Encode this manifest in its canonical form
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            entry.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = Error;
+
+    /// Decode a manifest from its canonical form
+    fn from_str(encoded: &str) -> Result<Self, Error> {
+        let mut entries = vec![];
+
+        for line in encoded.lines() {
+            let fields: Vec<_> = line.splitn(5, ' ').collect();
+
+            if fields.len() != 5 {
+                fail!(ErrorKind::Payload, "malformed manifest line: {:?}", line);
+            }
+
+            let mut entry = ManifestEntry {
+                path: fields[4].to_owned(),
+                size: fields[3].parse().map_err(|e| {
+                    ErrorKind::Payload.context(format!("invalid size {:?}: {}", fields[3], e))
+                })?,
+                mode: u32::from_str_radix(fields[2], 8).map_err(|e| {
+                    ErrorKind::Payload.context(format!("invalid mode {:?}: {}", fields[2], e))
+                })?,
+                sha256: [0u8; 32],
+                sha512: Sha512Digest([0u8; 64]),
+            };
+
+            decode_digest(fields[0], &mut entry.sha256)?;
+            decode_digest(fields[1], &mut entry.sha512.0)?;
+            entries.push(entry);
+        }
+
+        let manifest = Self { entries };
+        manifest.validate()?;
+
+        // Re-encoding must reproduce the original exactly, so every manifest
+        // has a single encoding (and hence a single root)
+        if manifest.to_string() != encoded {
+            fail!(ErrorKind::Payload, "manifest is not canonically encoded");
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// Decode a hex digest into the given buffer
+fn decode_digest(hex_digest: &str, digest: &mut [u8]) -> Result<(), Error> {
+    match hex::decode(hex_digest) {
+        Ok(ref bytes) if bytes.len() == digest.len() => {
+            digest.copy_from_slice(bytes);
+            Ok(())
+        }
+        _ => fail!(ErrorKind::Payload, "invalid digest: {:?}", hex_digest),
+    }
+}
+
+/// Computes the SHA-256 and SHA-512 digests of everything written to it
+#[derive(Default)]
+struct Hasher {
+    /// SHA-256 state
+    sha256: Sha256,
+
+    /// SHA-512 state
+    sha512: Sha512,
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sha256.input(buf);
+        self.sha512.input(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Add entries for all files within a directory to a manifest
+fn add_dir_entries(
+    dir: &Path,
+    prefix: &str,
+    entries: &mut Vec<ManifestEntry>,
+) -> Result<(), Error> {
+    for dir_entry in fs::read_dir(dir).map_err(|e| ErrorKind::Io.context(e))? {
+        let dir_entry = dir_entry.map_err(|e| ErrorKind::Io.context(e))?;
+        let file_type = dir_entry
+            .file_type()
+            .map_err(|e| ErrorKind::Io.context(e))?;
+
+        let name = match dir_entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(name) => fail!(ErrorKind::Build, "non-UTF-8 artifact path: {:?}", name),
+        };
+
+        let relative_path = format!("{}{}", prefix, name);
+
+        if file_type.is_dir() {
+            add_dir_entries(&dir_entry.path(), &format!("{}/", relative_path), entries)?;
+        } else if file_type.is_file() {
+            entries.push(ManifestEntry::from_file(&dir_entry.path(), relative_path)?);
+        } else {
+            fail!(
+                ErrorKind::Build,
+                "unsupported artifact type: {}",
+                relative_path
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Is the given file executable?
+#[cfg(unix)]
+pub(crate) fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+/// Is the given file executable?
+#[cfg(not(unix))]
+pub(crate) fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
@@ -0,0 +1,206 @@
+//! Participation in the commit-and-reveal protocol: builds requested crates
+//! and publishes commitments to (and then reveals of) their artifacts
+
+use crate::{
+    error::{Error, ErrorKind},
+    payload::Payload,
+    prelude::*,
+    state::{Build, BuildId, Params},
+    worker::Worker,
+};
+use std::{collections::BTreeSet, sync::Arc, thread, time::Duration};
+use synchro::types::account_address::AccountAddress;
+
+/// How often to poll the ledger for builds to participate in
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Access to the ledger needed to participate in builds
+pub trait Ledger {
+    /// Account this participant submits transactions from
+    fn sender(&self) -> AccountAddress;
+
+    /// Timestamp of the latest block (microseconds)
+    fn now(&self) -> Result<u64, Error>;
+
+    /// Protocol parameters of the group
+    fn params(&self) -> Result<Params, Error>;
+
+    /// Builds which have not yet reached a verdict
+    fn pending_builds(&self) -> Result<BTreeSet<BuildId>, Error>;
+
+    /// Get the record of a build
+    fn build(&self, build_id: &BuildId) -> Result<Option<Build>, Error>;
+
+    /// Sign and submit a transaction containing the given payload
+    fn submit(&self, payload: &Payload) -> Result<u64, Error>;
+}
+
+/// Ledgers shared between several users (e.g. a participant and an index
+/// watcher submitting from the same account)
+impl<L: Ledger + ?Sized> Ledger for Arc<L> {
+    fn sender(&self) -> AccountAddress {
+        (**self).sender()
+    }
+
+    fn now(&self) -> Result<u64, Error> {
+        (**self).now()
+    }
+
+    fn params(&self) -> Result<Params, Error> {
+        (**self).params()
+    }
+
+    fn pending_builds(&self) -> Result<BTreeSet<BuildId>, Error> {
+        (**self).pending_builds()
+    }
+
+    fn build(&self, build_id: &BuildId) -> Result<Option<Build>, Error> {
+        (**self).build(build_id)
+    }
+
+    fn submit(&self, payload: &Payload) -> Result<u64, Error> {
+        (**self).submit(payload)
+    }
+}
+
+/// Participant in the commit-and-reveal protocol
+pub struct Participant<L: Ledger> {
+    /// Ledger to participate through
+    ledger: L,
+
+    /// Worker which performs builds
+    worker: Worker,
+
+    /// Builds we've built (and submitted commitments for, if they succeeded)
+    committed: BTreeSet<BuildId>,
+
+    /// Builds we've submitted reveals for
+    revealed: BTreeSet<BuildId>,
+}
+
+impl<L: Ledger> Participant<L> {
+    /// Create a new participant
+    pub fn new(ledger: L, worker: Worker) -> Self {
+        Self {
+            ledger,
+            worker,
+            committed: BTreeSet::new(),
+            revealed: BTreeSet::new(),
+        }
+    }
+
+    /// Get the ledger this participant is using
+    pub fn ledger(&self) -> &L {
+        &self.ledger
+    }
+
+    /// Participate in pending builds forever
+    pub fn run(&mut self) -> ! {
+        loop {
+            if let Err(e) = self.worker.prune() {
+                status_warn!("couldn't prune old builds: {}", e);
+            }
+
+            if let Err(e) = self.step() {
+                status_warn!("error participating in builds: {}", e);
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Build, commit, and reveal pending builds as appropriate for the
+    /// current phase of each
+    pub fn step(&mut self) -> Result<(), Error> {
+        let sender = self.ledger.sender();
+
+        if !self.ledger.params()?.is_builder(&sender) {
+            return Ok(());
+        }
+
+        let now = self.ledger.now()?;
+
+        // Errors with one build shouldn't keep us from participating in
+        // the others, so they're logged rather than returned
+        for build_id in self.ledger.pending_builds()? {
+            let build = match self.ledger.build(&build_id) {
+                Ok(Some(build)) => build,
+                Ok(None) => continue,
+                Err(e) => {
+                    status_warn!("couldn't get build {}: {}", build_id, e);
+                    continue;
+                }
+            };
+
+            if build.is_committing(now)
+                && !build.commits.contains_key(&sender)
+                && !self.committed.contains(&build_id)
+            {
+                match self.commit(&build, &sender) {
+                    Ok(()) => {
+                        self.committed.insert(build_id);
+                    }
+                    Err(e) => status_warn!("couldn't commit to build {}: {}", build_id, e),
+                }
+            } else if build.is_revealing(now)
+                && build.commits.contains_key(&sender)
+                && !build.reveals.contains_key(&sender)
+                && !self.revealed.contains(&build_id)
+            {
+                match self.reveal(&build) {
+                    Ok(true) => {
+                        self.revealed.insert(build_id);
+                    }
+                    Ok(false) => (),
+                    Err(e) => status_warn!("couldn't reveal build {}: {}", build_id, e),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the requested crate and commit to the result.
+    ///
+    /// Only failing to submit the commitment is an error: builds which fail
+    /// are logged and not attempted again.
+    fn commit(&self, build: &Build, sender: &AccountAddress) -> Result<(), Error> {
+        let request = &build.request;
+
+        match self.worker.commit(request, sender) {
+            Ok(commit) => {
+                self.ledger.submit(&Payload::Commit(commit))?;
+            }
+            // Sources which don't match the registry index may have been
+            // tampered with, which is worth more than a warning
+            Err(ref e) if *e.kind() == ErrorKind::Checksum => status_err!(
+                "refusing to build {} {} ({}): {}",
+                request.crate_name,
+                request.version,
+                request.env.target,
+                e
+            ),
+            Err(e) => status_warn!(
+                "couldn't build {} {} ({}): {}",
+                request.crate_name,
+                request.version,
+                request.env.target,
+                e
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// Reveal the result we committed to for a build, returning whether
+    /// there was one to reveal
+    fn reveal(&self, build: &Build) -> Result<bool, Error> {
+        match self.worker.reveal(&build.request)? {
+            Some(reveal) => {
+                self.ledger.submit(&Payload::Reveal(reveal))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Synchronicity transaction payloads
+//!
+//! Synchronicity doesn't execute Move code. Instead, the `code` of a
+//! transaction's script carries a [`VersionedPayload`]: a version byte
+//! followed by one of the payloads below.
+//!
+//! Version 1 payload bodies are serialized using `bincode`'s default
+//! configuration (little endian, fixed-width integers, length-prefixed
+//! strings and sequences). All collections are ordered, so the encoding of
+//! a given payload is canonical, and decoding rejects anything else (e.g.
+//! trailing data) so each payload has exactly one encoding.
+
+mod build_env;
+
+pub use self::build_env::{BuildEnv, Profile, Toolchain};
+
+use crate::{
+    error::{Error, ErrorKind},
+    manifest::Manifest,
+    prelude::*,
+    registry::Registry,
+    state::{BuildId, Digest},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use synchro::{
+    transaction::{SignedTransaction, TransactionPayload, VersionedPayload},
+    types::account_address::AccountAddress,
+};
+
+/// Current version of the payload format
+pub const PAYLOAD_VERSION: u8 = 1;
+
+/// Maximum length of a crate name (same limit as crates.io)
+pub const MAX_CRATE_NAME_LENGTH: usize = 64;
+
+/// Maximum length of other string fields in a build request
+pub const MAX_FIELD_LENGTH: usize = 128;
+
+/// Synchronicity transaction payloads
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Payload {
+    /// Request that the group build a crate
+    BuildRequest(BuildRequest),
+
+    /// Commit to the artifact digest of a build without revealing it
+    Commit(Commit),
+
+    /// Reveal the artifact digests previously committed to
+    Reveal(Reveal),
+
+    /// Attest to the final result of a build
+    Attestation(Attestation),
+}
+
+impl Payload {
+    /// Decode a payload from the script of a signed transaction
+    pub fn from_transaction(txn: &SignedTransaction) -> Result<Self, Error> {
+        match VersionedPayload::from_transaction(txn) {
+            Some(payload) => Self::from_versioned(&payload),
+            None => fail!(
+                ErrorKind::Payload,
+                "not a Synchronicity transaction payload"
+            ),
+        }
+    }
+
+    /// Decode a payload from its serialized form
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        match VersionedPayload::from_bytes(bytes) {
+            Some(payload) => Self::from_versioned(&payload),
+            None => fail!(ErrorKind::Payload, "empty payload"),
+        }
+    }
+
+    /// Decode a payload from a `VersionedPayload`
+    pub fn from_versioned(payload: &VersionedPayload) -> Result<Self, Error> {
+        match payload.version {
+            PAYLOAD_VERSION => {
+                let decoded: Self = bincode::deserialize(&payload.body)
+                    .map_err(|e| ErrorKind::Payload.context(e))?;
+
+                // `bincode` ignores trailing data, so re-encoding is the only
+                // way to be sure the body is the canonical encoding
+                if decoded.to_versioned().body != payload.body {
+                    fail!(ErrorKind::Payload, "payload is not canonically encoded");
+                }
+
+                Ok(decoded)
+            }
+            version => fail!(
+                ErrorKind::Payload,
+                "unsupported payload version: {}",
+                version
+            ),
+        }
+    }
+
+    /// Check this payload is well-formed
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            Payload::BuildRequest(request) => request.validate(),
+            Payload::Reveal(reveal) => reveal.validate(),
+            Payload::Commit(_) | Payload::Attestation(_) => Ok(()),
+        }
+    }
+
+    /// Serialize this payload as a `VersionedPayload`
+    pub fn to_versioned(&self) -> VersionedPayload {
+        let body = bincode::serialize(self).expect("payload serialization failed");
+        VersionedPayload::new(PAYLOAD_VERSION, body)
+    }
+
+    /// Serialize this payload
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_versioned().to_bytes()
+    }
+
+    /// Wrap this payload in a `TransactionPayload` for signing
+    pub fn to_transaction_payload(&self) -> TransactionPayload {
+        self.to_versioned().to_transaction_payload()
+    }
+}
+
+/// Request to build a particular crate version
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BuildRequest {
+    /// Name of the crate
+    pub crate_name: String,
+
+    /// Version of the crate
+    pub version: String,
+
+    /// Index URL of the registry the crate is published to, or crates.io if
+    /// unset (see [`Registry::from_index_url`])
+    pub registry: Option<String>,
+
+    /// Environment to build in
+    pub env: BuildEnv,
+}
+
+impl BuildRequest {
+    /// Check this build request is well-formed
+    pub fn validate(&self) -> Result<(), Error> {
+        let valid_crate_name = !self.crate_name.is_empty()
+            && self.crate_name.len() <= MAX_CRATE_NAME_LENGTH
+            && self
+                .crate_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !valid_crate_name {
+            fail!(
+                ErrorKind::Payload,
+                "invalid crate name: {:?}",
+                self.crate_name
+            );
+        }
+
+        if self.version.is_empty() || self.version.len() > MAX_FIELD_LENGTH {
+            fail!(
+                ErrorKind::Payload,
+                "invalid crate version: {:?}",
+                self.version
+            );
+        }
+
+        self.registry()?;
+        self.env.validate()
+    }
+
+    /// Get the registry the crate is published to
+    pub fn registry(&self) -> Result<Registry, Error> {
+        Registry::from_index_url(self.registry.as_ref().map(String::as_str))
+    }
+
+    /// Compute the `BuildId` which identifies this request
+    pub fn id(&self) -> BuildId {
+        let encoded = bincode::serialize(self).expect("build request serialization failed");
+        let mut id = [0u8; 32];
+        id.copy_from_slice(&Sha256::digest(&encoded));
+        BuildId(id)
+    }
+}
+
+/// Commitment to the artifact digests of a build
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Commit {
+    /// Build being committed to
+    pub build_id: BuildId,
+
+    /// SHA-256 digest of the `.crate` tarball the builder built, after
+    /// verifying it against the registry index
+    pub source_digest: Digest,
+
+    /// Hiding commitment computed by [`commitment`]
+    pub commitment: Digest,
+}
+
+/// Reveal of previously committed artifact digests
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Reveal {
+    /// Build being revealed
+    pub build_id: BuildId,
+
+    /// Manifest of the build artifacts
+    pub manifest: Manifest,
+
+    /// Random salt used when computing the commitment
+    pub salt: [u8; 32],
+}
+
+impl Reveal {
+    /// Check this reveal is well-formed
+    pub fn validate(&self) -> Result<(), Error> {
+        self.manifest.validate()
+    }
+
+    /// Compute the artifact root (i.e. the root of the revealed manifest)
+    pub fn artifact_root(&self) -> Digest {
+        self.manifest.root()
+    }
+
+    /// Compute the commitment this reveal opens for the given builder, who
+    /// committed to building the given sources
+    pub fn commitment(&self, builder: &AccountAddress, source_digest: &Digest) -> Digest {
+        commitment(
+            &self.build_id,
+            builder,
+            source_digest,
+            &self.artifact_root(),
+            &self.salt,
+        )
+    }
+}
+
+/// Attestation by a builder to the final result of a build
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Attestation {
+    /// Build being attested to
+    pub build_id: BuildId,
+
+    /// Artifact root the builder attests to having reproduced
+    pub artifact_root: Digest,
+}
+
+/// Compute the hiding commitment to an artifact root.
+///
+/// The builder's address is included so one builder can't replay another
+/// builder's commitment as its own, and the source digest so the artifact
+/// root is bound to the sources it was built from.
+pub fn commitment(
+    build_id: &BuildId,
+    builder: &AccountAddress,
+    source_digest: &Digest,
+    artifact_root: &Digest,
+    salt: &[u8; 32],
+) -> Digest {
+    let mut hasher = Sha256::new();
+    hasher.input(&build_id.0);
+    hasher.input(builder.as_ref());
+    hasher.input(source_digest);
+    hasher.input(artifact_root);
+    hasher.input(salt);
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hasher.result());
+    result
+}
@@ -0,0 +1,299 @@
+//! Build environment descriptors: everything besides the crate's source
+//! which affects the output of a build, pinned exactly.
+//!
+//! A builder which can't reproduce a descriptor exactly must refuse to build
+//! rather than approximate it, as its result would be meaningless.
+
+use super::MAX_FIELD_LENGTH;
+use crate::{
+    error::{Error, ErrorKind},
+    prelude::*,
+    state::Digest,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+};
+
+/// Maximum number of features which can be enabled
+pub const MAX_FEATURES: usize = 64;
+
+/// Maximum number of `RUSTFLAGS`
+pub const MAX_RUSTFLAGS: usize = 32;
+
+/// Description of the environment a build is performed in
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BuildEnv {
+    /// Rust toolchain to build with
+    pub toolchain: Toolchain,
+
+    /// Target triple to build for
+    pub target: String,
+
+    /// Cargo features to enable
+    pub features: BTreeSet<String>,
+
+    /// Disable the crate's default features
+    pub no_default_features: bool,
+
+    /// Cargo profile to build with
+    pub profile: Profile,
+
+    /// Value of `SOURCE_DATE_EPOCH` during the build
+    pub source_date_epoch: u64,
+
+    /// Path the build directory is remapped to in build outputs (via
+    /// `--remap-path-prefix`) so outputs don't depend on where the build
+    /// happened. If unset, no remapping is performed.
+    pub remap_path_prefix: Option<String>,
+
+    /// Additional `RUSTFLAGS`
+    pub rustflags: Vec<String>,
+
+    /// SHA-256 digest of the `Cargo.lock` the crate must ship with. If set,
+    /// builders refuse to build crates whose lockfile doesn't match, and
+    /// build with it as-is (i.e. `--locked`)
+    pub lockfile: Option<Digest>,
+}
+
+impl BuildEnv {
+    /// Check this build environment descriptor is well-formed
+    pub fn validate(&self) -> Result<(), Error> {
+        self.toolchain.validate()?;
+
+        let valid_target = !self.target.is_empty()
+            && self.target.len() <= MAX_FIELD_LENGTH
+            && self
+                .target
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+
+        if !valid_target {
+            fail!(
+                ErrorKind::Payload,
+                "invalid target triple: {:?}",
+                self.target
+            );
+        }
+
+        if self.features.len() > MAX_FEATURES {
+            fail!(
+                ErrorKind::Payload,
+                "too many features: {}",
+                self.features.len()
+            );
+        }
+
+        for feature in &self.features {
+            let valid_feature = !feature.is_empty()
+                && feature.len() <= MAX_FIELD_LENGTH
+                && feature
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '/');
+
+            if !valid_feature {
+                fail!(ErrorKind::Payload, "invalid feature: {:?}", feature);
+            }
+        }
+
+        if let Some(prefix) = &self.remap_path_prefix {
+            let valid_prefix = prefix.starts_with('/')
+                && prefix.len() <= MAX_FIELD_LENGTH
+                && !prefix.chars().any(|c| c.is_whitespace() || c.is_control());
+
+            if !valid_prefix {
+                fail!(
+                    ErrorKind::Payload,
+                    "invalid remap path prefix: {:?}",
+                    prefix
+                );
+            }
+        }
+
+        if self.rustflags.len() > MAX_RUSTFLAGS {
+            fail!(
+                ErrorKind::Payload,
+                "too many RUSTFLAGS: {}",
+                self.rustflags.len()
+            );
+        }
+
+        for flag in &self.rustflags {
+            // `RUSTFLAGS` is whitespace-separated. Path remapping depends on
+            // where each builder builds, so it's only expressible via
+            // `remap_path_prefix`
+            let valid_flag = !flag.is_empty()
+                && flag.len() <= MAX_FIELD_LENGTH
+                && !flag.chars().any(|c| c.is_whitespace() || c.is_control())
+                && !flag.starts_with("--remap-path-prefix");
+
+            if !valid_flag {
+                fail!(ErrorKind::Payload, "invalid RUSTFLAG: {:?}", flag);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rust toolchain, pinned to a particular release by date and/or commit hash
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Toolchain {
+    /// Release channel (`stable`, `beta`, `nightly`) or version (`1.39.0`)
+    pub channel: String,
+
+    /// Date of the release on the channel (`YYYY-MM-DD`)
+    pub date: Option<String>,
+
+    /// Commit hash of `rustc` (as reported by `rustc -vV`, or a prefix
+    /// of at least 7 hex digits)
+    pub commit_hash: Option<String>,
+}
+
+impl Toolchain {
+    /// Check this toolchain is well-formed and pinned
+    pub fn validate(&self) -> Result<(), Error> {
+        let valid_channel = !self.channel.is_empty()
+            && self.channel.len() <= MAX_FIELD_LENGTH
+            && self
+                .channel
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+
+        if !valid_channel {
+            fail!(
+                ErrorKind::Payload,
+                "invalid toolchain channel: {:?}",
+                self.channel
+            );
+        }
+
+        if let Some(date) = &self.date {
+            let valid_date = date.len() == 10
+                && date.char_indices().all(|(i, c)| match i {
+                    4 | 7 => c == '-',
+                    _ => c.is_ascii_digit(),
+                });
+
+            if !valid_date {
+                fail!(ErrorKind::Payload, "invalid toolchain date: {:?}", date);
+            }
+        }
+
+        if let Some(hash) = &self.commit_hash {
+            let valid_hash = hash.len() >= 7
+                && hash.len() <= 40
+                && hash
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+
+            if !valid_hash {
+                fail!(
+                    ErrorKind::Payload,
+                    "invalid toolchain commit hash: {:?}",
+                    hash
+                );
+            }
+        }
+
+        if self.date.is_none() && self.commit_hash.is_none() {
+            fail!(
+                ErrorKind::Payload,
+                "toolchain {:?} isn't pinned by date or commit hash",
+                self.channel
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Name of the toolchain as understood by `rustup` (e.g.
+    /// `nightly-2019-11-01`)
+    pub fn rustup_name(&self) -> String {
+        match &self.date {
+            Some(date) => format!("{}-{}", self.channel, date),
+            None => self.channel.clone(),
+        }
+    }
+
+    /// Check the version information printed by `rustc -vV` matches this
+    /// toolchain. The date can't be checked this way: `rustup` is trusted to
+    /// install the release for the requested date.
+    pub fn check_version_info(&self, version_info: &str) -> Result<(), Error> {
+        let field = |name: &str| {
+            version_info
+                .lines()
+                .find(|line| line.starts_with(name) && line[name.len()..].starts_with(": "))
+                .map(|line| line[name.len() + 2..].trim().to_owned())
+                .unwrap_or_default()
+        };
+
+        let release = field("release");
+
+        let channel_matches = match self.channel.as_str() {
+            "stable" => !release.is_empty() && !release.contains('-'),
+            "beta" | "nightly" => {
+                release.ends_with(&format!("-{}", self.channel))
+                    || release.contains(&format!("-{}.", self.channel))
+            }
+            version => release == version,
+        };
+
+        if !channel_matches {
+            fail!(
+                ErrorKind::Build,
+                "rustc release {:?} isn't on channel {:?}",
+                release,
+                self.channel
+            );
+        }
+
+        if let Some(hash) = &self.commit_hash {
+            let actual_hash = field("commit-hash");
+
+            if !actual_hash.starts_with(hash.as_str()) {
+                fail!(
+                    ErrorKind::Build,
+                    "rustc commit hash {:?} doesn't match {:?}",
+                    actual_hash,
+                    hash
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rustup_name())?;
+
+        if let Some(hash) = &self.commit_hash {
+            write!(f, " ({})", hash)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Cargo profiles
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Profile {
+    /// Development profile (i.e. `cargo build`)
+    Dev,
+
+    /// Release profile (i.e. `cargo build --release`)
+    Release,
+}
+
+impl Profile {
+    /// Name of the subdirectory of `target/<triple>` outputs are placed in
+    pub fn target_subdir(self) -> &'static str {
+        match self {
+            Profile::Dev => "debug",
+            Profile::Release => "release",
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! Crate registries: crates.io, and alternative registries identified by
+//! index URL.
+//!
+//! Alternative registries are supported via a sparse HTTP index
+//! (`sparse+https://...`). The index's `config.json` gives the template
+//! `.crate` files are downloaded from, as described in the Cargo book:
+//! <https://doc.rust-lang.org/cargo/reference/registries.html#index-format>
+//!
+//! Indexes in a local directory are only ever configured by a builder's
+//! backend (e.g. a local mirror), never named by build requests: a `file://`
+//! URL would be resolved against each builder's own filesystem.
+//!
+//! Downloaded `.crate` files are always checked against the `cksum` in the
+//! index, so builders never build sources other than those published.
+
+use crate::{
+    error::{Error, ErrorKind},
+    prelude::*,
+    state::Digest,
+};
+use serde::Deserialize;
+use sha2::{Digest as _, Sha256};
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+/// Alias which always refers to crates.io
+pub const CRATES_IO_ALIAS: &str = "crates-io";
+
+/// Sparse index of crates.io
+pub const CRATES_IO_INDEX: &str = "https://index.crates.io/";
+
+/// Prefix of sparse index URLs
+pub const SPARSE_PREFIX: &str = "sparse+";
+
+/// Prefix of local download URLs (only followed for local indexes)
+pub const FILE_PREFIX: &str = "file://";
+
+/// Maximum length of a registry index URL
+pub const MAX_INDEX_URL_LENGTH: usize = 256;
+
+/// Index configuration file
+pub const CONFIG_FILE: &str = "config.json";
+
+/// A published crate version, as listed in a registry index
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+pub struct IndexEntry {
+    /// Crate name
+    pub name: String,
+
+    /// Crate version
+    pub vers: String,
+
+    /// SHA-256 checksum of the `.crate` file (hex)
+    pub cksum: String,
+
+    /// Has this version been yanked?
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+/// Registry index configuration (i.e. `config.json`)
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+pub struct IndexConfig {
+    /// Download URL (template) for `.crate` files
+    pub dl: String,
+}
+
+/// Registries crates can be fetched from
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Registry {
+    /// crates.io (via its sparse index)
+    CratesIo,
+
+    /// Registry with a sparse HTTP index at the given base URL
+    Sparse(String),
+
+    /// Registry with an index in the given local directory
+    File(PathBuf),
+}
+
+impl Registry {
+    /// Parse a registry index URL, or crates.io if there is none
+    pub fn from_index_url(index_url: Option<&str>) -> Result<Self, Error> {
+        let url = match index_url {
+            Some(url) => url,
+            None => return Ok(Registry::CratesIo),
+        };
+
+        if url.len() > MAX_INDEX_URL_LENGTH
+            || url.chars().any(|c| c.is_whitespace() || c.is_control())
+        {
+            fail!(ErrorKind::Payload, "invalid registry index URL: {:?}", url);
+        }
+
+        if url.starts_with(SPARSE_PREFIX) {
+            let base = &url[SPARSE_PREFIX.len()..];
+
+            // The index is what `.crate` checksums are checked against, so
+            // it must not be fetched over plain HTTP
+            if !base.starts_with("https://") {
+                fail!(
+                    ErrorKind::Payload,
+                    "sparse index URLs must use https: {:?}",
+                    url
+                );
+            }
+
+            let base = if base.ends_with('/') {
+                base.to_owned()
+            } else {
+                format!("{}/", base)
+            };
+
+            Ok(Registry::Sparse(base))
+        } else {
+            fail!(
+                ErrorKind::Payload,
+                "unsupported registry index URL (expected sparse+https://): {:?}",
+                url
+            );
+        }
+    }
+
+    /// Fetch the registry's index configuration
+    pub fn config(&self) -> Result<IndexConfig, Error> {
+        let bytes = self.fetch_index_file(Path::new(CONFIG_FILE))?;
+        serde_json::from_slice(&bytes).map_err(|e| ErrorKind::Registry.context(e).into())
+    }
+
+    /// Look up a crate version in the registry's index
+    pub fn entry(&self, crate_name: &str, version: &str) -> Result<IndexEntry, Error> {
+        let bytes = self.fetch_index_file(&crate_path(crate_name))?;
+        let contents = String::from_utf8(bytes).map_err(|e| ErrorKind::Registry.context(e))?;
+
+        match parse_entries(&contents)
+            .into_iter()
+            .find(|entry| entry.name == crate_name && entry.vers == version)
+        {
+            Some(entry) => Ok(entry),
+            None => fail!(
+                ErrorKind::Registry,
+                "{} {} not found in registry index",
+                crate_name,
+                version
+            ),
+        }
+    }
+
+    /// Download a crate version's `.crate` file to the given path and verify
+    /// it against the checksum in the index, returning its SHA-256 digest.
+    ///
+    /// If the checksum doesn't match, the file is removed and an
+    /// `ErrorKind::Checksum` error is returned.
+    pub fn download(&self, crate_name: &str, version: &str, dest: &Path) -> Result<Digest, Error> {
+        let entry = self.entry(crate_name, version)?;
+        let url = download_url(&self.config()?.dl, &entry);
+
+        if url.starts_with(FILE_PREFIX) {
+            // Only a local index may point at local files: a remote one could
+            // otherwise have builders copy (and report the digest of) any file
+            if let Registry::File(_) = self {
+                fs::copy(&url[FILE_PREFIX.len()..], dest).map_err(|e| {
+                    ErrorKind::Registry.context(format!("couldn't copy {}: {}", url, e))
+                })?;
+            } else {
+                fail!(
+                    ErrorKind::Registry,
+                    "remote registries can't download from file:// URLs: {:?}",
+                    url
+                );
+            }
+        } else if url.starts_with("https://") {
+            let mut file = File::create(dest).map_err(|e| ErrorKind::Io.context(e))?;
+            http_get(&url)?
+                .copy_to(&mut file)
+                .map_err(|e| ErrorKind::Registry.context(e))?;
+        } else {
+            fail!(
+                ErrorKind::Registry,
+                "registry download URLs must use https: {:?}",
+                url
+            );
+        }
+
+        let digest = file_digest(dest)?;
+
+        if hex::encode(digest) != entry.cksum.to_ascii_lowercase() {
+            fs::remove_file(dest).map_err(|e| ErrorKind::Io.context(e))?;
+
+            fail!(
+                ErrorKind::Checksum,
+                "{} {} doesn't match the registry index (expected SHA-256 {}, got {})",
+                crate_name,
+                version,
+                entry.cksum,
+                hex::encode(digest)
+            );
+        }
+
+        Ok(digest)
+    }
+
+    /// Fetch a file from the index by its path relative to the index root
+    fn fetch_index_file(&self, path: &Path) -> Result<Vec<u8>, Error> {
+        match self {
+            Registry::CratesIo => http_get_bytes(&format!("{}{}", CRATES_IO_INDEX, path.display())),
+            Registry::Sparse(base) => http_get_bytes(&format!("{}{}", base, path.display())),
+            Registry::File(dir) => fs::read(dir.join(path)).map_err(|e| {
+                ErrorKind::Registry
+                    .context(format!("couldn't read {}: {}", dir.join(path).display(), e))
+                    .into()
+            }),
+        }
+    }
+}
+
+/// Get the path of a crate's file within an index
+pub fn crate_path(name: &str) -> PathBuf {
+    let name = name.to_ascii_lowercase();
+    Path::new(&crate_prefix(&name)).join(name)
+}
+
+/// Parse the entries in a crate's index file, skipping malformed lines
+pub fn parse_entries(contents: &str) -> Vec<IndexEntry> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Directory containing a crate's file within an index (case is preserved)
+fn crate_prefix(name: &str) -> String {
+    match name.len() {
+        1 => "1".to_owned(),
+        2 => "2".to_owned(),
+        3 => format!("3/{}", &name[..1]),
+        _ => format!("{}/{}", &name[..2], &name[2..4]),
+    }
+}
+
+/// Expand an index's download URL template for a crate version
+fn download_url(template: &str, entry: &IndexEntry) -> String {
+    const MARKERS: &[&str] = &[
+        "{crate}",
+        "{version}",
+        "{prefix}",
+        "{lowerprefix}",
+        "{sha256-checksum}",
+    ];
+
+    if !MARKERS.iter().any(|marker| template.contains(marker)) {
+        return format!(
+            "{}/{}/{}/download",
+            template.trim_end_matches('/'),
+            entry.name,
+            entry.vers
+        );
+    }
+
+    template
+        .replace("{crate}", &entry.name)
+        .replace("{version}", &entry.vers)
+        .replace("{prefix}", &crate_prefix(&entry.name))
+        .replace(
+            "{lowerprefix}",
+            &crate_prefix(&entry.name.to_ascii_lowercase()),
+        )
+        .replace("{sha256-checksum}", &entry.cksum)
+}
+
+/// Compute the SHA-256 digest of a file
+fn file_digest(path: &Path) -> Result<Digest, Error> {
+    let mut file = File::open(path).map_err(|e| ErrorKind::Io.context(e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| ErrorKind::Io.context(e))?;
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&hasher.result());
+    Ok(digest)
+}
+
+/// Fetch the body of a URL
+fn http_get_bytes(url: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![];
+
+    http_get(url)?
+        .read_to_end(&mut bytes)
+        .map_err(|e| ErrorKind::Registry.context(e))?;
+
+    Ok(bytes)
+}
+
+/// Perform an HTTP GET request, failing unless it succeeds
+fn http_get(url: &str) -> Result<reqwest::Response, Error> {
+    let response = reqwest::get(url)
+        .map_err(|e| ErrorKind::Registry.context(format!("couldn't fetch {}: {}", url, e)))?;
+
+    if !response.status().is_success() {
+        fail!(
+            ErrorKind::Registry,
+            "couldn't fetch {}: {}",
+            url,
+            response.status()
+        );
+    }
+
+    Ok(response)
+}
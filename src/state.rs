@@ -0,0 +1,479 @@
+//! Ledger state kept by the Synchronicity state machine
+
+use crate::{manifest::Manifest, payload::BuildRequest};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+};
+use synchro::{
+    error::Error,
+    state_view::StateView,
+    types::{
+        access_path::AccessPath,
+        account_address::{AccountAddress, ADDRESS_LENGTH},
+        write_set::{WriteOp, WriteSet, WriteSetMut},
+    },
+};
+
+/// Path under each account where its Synchronicity sequence number is kept
+pub const SEQUENCE_NUMBER_PATH: &[u8] = b"synchronicity/sequence_number";
+
+/// Path prefix for build records
+pub const BUILD_PATH_PREFIX: &[u8] = b"synchronicity/build/";
+
+/// Path to the counter for events emitted by the state machine
+pub const EVENT_COUNT_PATH: &[u8] = b"synchronicity/event_count";
+
+/// Path to the group's protocol parameters
+pub const PARAMS_PATH: &[u8] = b"synchronicity/params";
+
+/// Path to the timestamp of the most recent block (in microseconds)
+pub const TIMESTAMP_PATH: &[u8] = b"synchronicity/timestamp";
+
+/// Path to the set of builds which haven't reached a terminal phase
+pub const PENDING_BUILDS_PATH: &[u8] = b"synchronicity/pending_builds";
+
+/// Path prefix for verdicts
+pub const VERDICT_PATH_PREFIX: &[u8] = b"synchronicity/verdict/";
+
+/// Path to the accumulator of the reproducibility log (under `log_account`)
+pub const LOG_PATH: &[u8] = b"synchronicity/log";
+
+/// Path prefix for leaves of the reproducibility log
+pub const LOG_LEAF_PATH_PREFIX: &[u8] = b"synchronicity/log/leaf/";
+
+/// Default amount of time builders have to commit to a build (1 hour)
+pub const DEFAULT_COMMIT_TIMEOUT_USECS: u64 = 60 * 60 * 1_000_000;
+
+/// Default amount of time builders have to reveal their results (10 minutes)
+pub const DEFAULT_REVEAL_TIMEOUT_USECS: u64 = 10 * 60 * 1_000_000;
+
+/// SHA-256 digest
+pub type Digest = [u8; 32];
+
+/// Account which holds the group's small, fixed-size state: parameters, the
+/// latest block timestamp, the event counter, and the set of pending builds.
+///
+/// Builds, verdicts, and log leaves are each kept under their own account
+/// (see `record_account`), since an account's state is read and written as
+/// a whole.
+pub fn state_account() -> AccountAddress {
+    AccountAddress::default()
+}
+
+/// Account which holds the record at the given path and nothing else,
+/// derived from the SHA-256 of the path
+pub fn record_account(path: &[u8]) -> AccountAddress {
+    let mut address = [0; ADDRESS_LENGTH];
+    address.copy_from_slice(&Sha256::digest(path));
+    AccountAddress::new(address)
+}
+
+/// Access path for a record kept under its own account
+fn record_path(path: Vec<u8>) -> AccessPath {
+    AccessPath::new(record_account(&path), path)
+}
+
+/// Account which holds the reproducibility log's accumulator and nothing
+/// else, so proofs against the log only need to carry the accumulator
+pub fn log_account() -> AccountAddress {
+    let mut address = [0; ADDRESS_LENGTH];
+    address[ADDRESS_LENGTH - 1] = 1;
+    AccountAddress::new(address)
+}
+
+/// Access path for an account's sequence number
+pub fn sequence_number_path(account: AccountAddress) -> AccessPath {
+    AccessPath::new(account, SEQUENCE_NUMBER_PATH.to_vec())
+}
+
+/// Access path for a build record
+pub fn build_path(build_id: &BuildId) -> AccessPath {
+    let mut path = BUILD_PATH_PREFIX.to_vec();
+    path.extend_from_slice(&build_id.0);
+    record_path(path)
+}
+
+/// Access path for the event counter
+pub fn event_count_path() -> AccessPath {
+    AccessPath::new(state_account(), EVENT_COUNT_PATH.to_vec())
+}
+
+/// Access path for the protocol parameters
+pub fn params_path() -> AccessPath {
+    AccessPath::new(state_account(), PARAMS_PATH.to_vec())
+}
+
+/// Access path for the latest block timestamp
+pub fn timestamp_path() -> AccessPath {
+    AccessPath::new(state_account(), TIMESTAMP_PATH.to_vec())
+}
+
+/// Access path for the set of pending builds
+pub fn pending_builds_path() -> AccessPath {
+    AccessPath::new(state_account(), PENDING_BUILDS_PATH.to_vec())
+}
+
+/// Access path for the verdict on a particular crate, version, and target,
+/// in the registry with the given index URL (or crates.io if none)
+pub fn verdict_path(
+    registry: Option<&str>,
+    crate_name: &str,
+    version: &str,
+    target: &str,
+) -> AccessPath {
+    let mut path = VERDICT_PATH_PREFIX.to_vec();
+    path.extend(
+        bincode::serialize(&(registry, crate_name, version, target))
+            .expect("verdict key serialization failed"),
+    );
+    record_path(path)
+}
+
+/// Access path for the verdict on the crate version and target named in a
+/// build request
+pub fn request_verdict_path(request: &BuildRequest) -> AccessPath {
+    verdict_path(
+        request.registry.as_ref().map(String::as_str),
+        &request.crate_name,
+        &request.version,
+        &request.env.target,
+    )
+}
+
+/// Access path for the reproducibility log's accumulator
+pub fn log_path() -> AccessPath {
+    AccessPath::new(log_account(), LOG_PATH.to_vec())
+}
+
+/// Access path for a leaf of the reproducibility log
+pub fn log_leaf_path(index: u64) -> AccessPath {
+    let mut path = LOG_LEAF_PATH_PREFIX.to_vec();
+    path.extend_from_slice(&index.to_be_bytes());
+    record_path(path)
+}
+
+/// Write set which initializes the state with the given parameters, for use
+/// as the genesis transaction
+pub fn genesis_write_set(params: &Params) -> WriteSet {
+    let params_bytes = bincode::serialize(params).expect("params serialization failed");
+
+    WriteSetMut::new(vec![(params_path(), WriteOp::Value(params_bytes))])
+        .freeze()
+        .expect("write set should be valid")
+}
+
+/// Protocol parameters for the group (written at genesis)
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+    /// Accounts allowed to commit and reveal build results.
+    /// If empty, any account may act as a builder, but no build can be
+    /// reproduced (see `Params::threshold`).
+    pub builders: BTreeSet<AccountAddress>,
+
+    /// Time builders have to commit after a build is requested (microseconds)
+    pub commit_timeout_usecs: u64,
+
+    /// Time builders have to reveal after the commit phase ends (microseconds)
+    pub reveal_timeout_usecs: u64,
+
+    /// Number of matching reveals needed to consider a build reproduced.
+    /// If unset, a 2f+1 quorum of the builders is required.
+    pub threshold: Option<usize>,
+}
+
+impl Params {
+    /// Is the given account allowed to act as a builder?
+    pub fn is_builder(&self, account: &AccountAddress) -> bool {
+        self.builders.is_empty() || self.builders.contains(account)
+    }
+
+    /// Number of matching reveals needed to consider a build reproduced.
+    ///
+    /// Returns `None` when the builder set is open (i.e. `builders` is
+    /// empty): anyone can commit under as many accounts as they like then, so
+    /// no number of matching reveals shows a build was reproduced.
+    pub fn threshold(&self) -> Option<usize> {
+        if self.builders.is_empty() {
+            return None;
+        }
+
+        if let Some(threshold) = self.threshold {
+            return Some(threshold.max(1));
+        }
+
+        // 2f+1 where n = 3f+1
+        let n = self.builders.len();
+        Some(n - (n - 1) / 3)
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            builders: BTreeSet::new(),
+            commit_timeout_usecs: DEFAULT_COMMIT_TIMEOUT_USECS,
+            reveal_timeout_usecs: DEFAULT_REVEAL_TIMEOUT_USECS,
+            threshold: None,
+        }
+    }
+}
+
+/// Phases of the commit-and-reveal protocol for a build
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Phase {
+    /// Build requested, no commitments yet
+    Open,
+
+    /// At least one builder has committed
+    Committing,
+
+    /// Commit phase over: builders may reveal until the given deadline
+    Revealing {
+        /// Time after which reveals are no longer accepted (microseconds)
+        deadline: u64,
+    },
+
+    /// Reveal phase over: the build's result is final
+    Finalized,
+
+    /// No builder committed before the deadline
+    Expired,
+}
+
+impl Phase {
+    /// Is this a terminal phase?
+    pub fn is_terminal(self) -> bool {
+        match self {
+            Phase::Finalized | Phase::Expired => true,
+            _ => false,
+        }
+    }
+}
+
+/// Build identifiers (SHA-256 of the serialized `BuildRequest`)
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct BuildId(pub [u8; 32]);
+
+impl Display for BuildId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Record of a requested build and the results builders have published
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Build {
+    /// Original build request
+    pub request: BuildRequest,
+
+    /// Account which requested the build
+    pub requester: AccountAddress,
+
+    /// Current phase of the commit-and-reveal protocol
+    pub phase: Phase,
+
+    /// Time after which commitments are no longer accepted (microseconds)
+    pub commit_deadline: u64,
+
+    /// Commitments published by each builder
+    pub commits: BTreeMap<AccountAddress, Digest>,
+
+    /// Source digests each builder committed to having built
+    pub sources: BTreeMap<AccountAddress, Digest>,
+
+    /// Artifact roots revealed by each builder
+    pub reveals: BTreeMap<AccountAddress, Digest>,
+
+    /// Manifest for each revealed artifact root
+    pub manifests: BTreeMap<Digest, Manifest>,
+
+    /// Builders which have attested to their revealed artifact root
+    pub attestations: BTreeSet<AccountAddress>,
+}
+
+impl Build {
+    /// Create a new build record from a request made at the given time
+    pub fn new(
+        request: BuildRequest,
+        requester: AccountAddress,
+        now: u64,
+        params: &Params,
+    ) -> Self {
+        Self {
+            request,
+            requester,
+            phase: Phase::Open,
+            commit_deadline: now.saturating_add(params.commit_timeout_usecs),
+            commits: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            reveals: BTreeMap::new(),
+            manifests: BTreeMap::new(),
+            attestations: BTreeSet::new(),
+        }
+    }
+
+    /// Are commitments being accepted at the given time?
+    pub fn is_committing(&self, now: u64) -> bool {
+        match self.phase {
+            Phase::Open | Phase::Committing => now < self.commit_deadline,
+            _ => false,
+        }
+    }
+
+    /// Are reveals being accepted at the given time?
+    pub fn is_revealing(&self, now: u64) -> bool {
+        match self.phase {
+            Phase::Revealing { deadline } => now < deadline,
+            _ => false,
+        }
+    }
+
+    /// Advance this build to the phase it should be in at the given time.
+    ///
+    /// Returns `true` if the phase changed.
+    pub fn advance(&mut self, now: u64, params: &Params) -> bool {
+        let all_committed = !params.builders.is_empty()
+            && params
+                .builders
+                .iter()
+                .all(|builder| self.commits.contains_key(builder));
+
+        let next_phase = match self.phase {
+            Phase::Open | Phase::Committing if self.commits.is_empty() => {
+                if now >= self.commit_deadline {
+                    Phase::Expired
+                } else {
+                    Phase::Open
+                }
+            }
+            Phase::Open | Phase::Committing => {
+                if now >= self.commit_deadline || all_committed {
+                    let start = now.min(self.commit_deadline);
+                    Phase::Revealing {
+                        deadline: start.saturating_add(params.reveal_timeout_usecs),
+                    }
+                } else {
+                    Phase::Committing
+                }
+            }
+            Phase::Revealing { deadline } => {
+                if now >= deadline || self.reveals.len() == self.commits.len() {
+                    Phase::Finalized
+                } else {
+                    self.phase
+                }
+            }
+            phase => phase,
+        };
+
+        if next_phase == self.phase {
+            false
+        } else {
+            self.phase = next_phase;
+            true
+        }
+    }
+}
+
+/// Events emitted by the state machine
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    /// A build was requested
+    BuildRequested(BuildId),
+
+    /// A builder committed to a build result
+    Committed(BuildId, AccountAddress),
+
+    /// A builder revealed a build result
+    Revealed(BuildId, AccountAddress),
+
+    /// A builder attested to a build result
+    Attested(BuildId, AccountAddress),
+
+    /// A build moved to a new phase
+    PhaseChanged(BuildId, Phase),
+
+    /// A verdict was reached on a build
+    VerdictReached(BuildId),
+}
+
+/// Cache of ledger state read and written while executing a block.
+///
+/// Writes made by the transaction currently executing are kept pending until
+/// it either commits them (making them visible to subsequent transactions in
+/// the same block) or discards them.
+pub struct StateCache<'a> {
+    /// Ledger state as of the parent block
+    view: &'a dyn StateView,
+
+    /// Writes made by previous transactions in this block
+    committed: BTreeMap<AccessPath, Vec<u8>>,
+
+    /// Writes made by the transaction currently being executed
+    pending: BTreeMap<AccessPath, Vec<u8>>,
+}
+
+impl<'a> StateCache<'a> {
+    /// Create a new state cache on top of the given state view
+    pub fn new(view: &'a dyn StateView) -> Self {
+        Self {
+            view,
+            committed: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Is this the genesis state?
+    pub fn is_genesis(&self) -> bool {
+        self.view.is_genesis()
+    }
+
+    /// Get a raw value from the state
+    pub fn get_raw(&self, path: &AccessPath) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(value) = self.pending.get(path).or_else(|| self.committed.get(path)) {
+            return Ok(Some(value.clone()));
+        }
+
+        self.view.get(path)
+    }
+
+    /// Get and deserialize a value from the state
+    pub fn get<T: DeserializeOwned>(&self, path: &AccessPath) -> Result<Option<T>, Error> {
+        match self.get_raw(path)? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Serialize and write a value to the state
+    pub fn put<T: Serialize>(&mut self, path: AccessPath, value: &T) -> Result<(), Error> {
+        self.pending.insert(path, bincode::serialize(value)?);
+        Ok(())
+    }
+
+    /// Commit pending writes, returning them as a `WriteSet`
+    pub fn commit(&mut self) -> WriteSet {
+        let pending = std::mem::replace(&mut self.pending, BTreeMap::new());
+        let mut write_set = Vec::with_capacity(pending.len());
+
+        for (path, value) in pending {
+            write_set.push((path.clone(), WriteOp::Value(value.clone())));
+            self.committed.insert(path, value);
+        }
+
+        WriteSetMut::new(write_set)
+            .freeze()
+            .expect("write set should be valid")
+    }
+
+    /// Discard pending writes
+    pub fn discard(&mut self) {
+        self.pending.clear();
+    }
+}
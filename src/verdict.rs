@@ -0,0 +1,236 @@
+//! Reproducibility verdicts: the final result of a build
+
+use crate::{
+    manifest::{Manifest, ManifestEntry},
+    payload::BuildRequest,
+    state::{self, Build, BuildId, Digest, Params},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use synchro::{error::Error, state_view::StateView, types::account_address::AccountAddress};
+
+/// Final result of a build, as agreed upon by the group
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Verdict {
+    /// Build this verdict is for
+    pub build_id: BuildId,
+
+    /// Original build request
+    pub request: BuildRequest,
+
+    /// Time the verdict was reached (microseconds)
+    pub finalized_at: u64,
+
+    /// Number of matching reveals which were required (`None` if the group
+    /// has no builder set, in which case no build can be reproduced)
+    pub threshold: Option<usize>,
+
+    /// Index of this verdict in the reproducibility log
+    pub log_index: u64,
+
+    /// Outcome of the build
+    pub status: VerdictStatus,
+}
+
+/// Outcomes of a build
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum VerdictStatus {
+    /// A threshold of builders built the same sources and revealed the same
+    /// artifact root
+    Reproduced {
+        /// Digest of the `.crate` tarball the threshold built
+        source_digest: Digest,
+
+        /// Artifact root the threshold agreed upon
+        artifact_root: Digest,
+
+        /// Manifest of the artifacts under the agreed-upon root
+        manifest: Manifest,
+
+        /// Builders who revealed the agreed-upon artifact root
+        builders: BTreeSet<AccountAddress>,
+
+        /// Builders who built different sources or revealed a different
+        /// artifact root (mapped to the result they revealed)
+        dissenters: BTreeMap<AccountAddress, BuildResult>,
+    },
+
+    /// Enough builders revealed, but no artifact root reached the threshold
+    Diverged {
+        /// Digest of the `.crate` tarball the plurality built
+        source_digest: Digest,
+
+        /// Most commonly revealed artifact root
+        plurality_root: Digest,
+
+        /// Builders who built different sources or revealed something other
+        /// than the plurality root (mapped to the result they revealed)
+        dissenters: BTreeMap<AccountAddress, BuildResult>,
+    },
+
+    /// Too few builders revealed to reach the threshold
+    InsufficientParticipation {
+        /// Number of builders who revealed
+        revealed: usize,
+    },
+}
+
+/// Result a builder revealed for a build
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct BuildResult {
+    /// Digest of the `.crate` tarball the builder committed to having built
+    pub source_digest: Digest,
+
+    /// Artifact root the builder revealed
+    pub artifact_root: Digest,
+}
+
+impl Verdict {
+    /// Decide the verdict for a build whose reveal phase has closed, which
+    /// will be stored in the reproducibility log at the given index
+    pub fn decide(
+        build_id: BuildId,
+        build: &Build,
+        now: u64,
+        params: &Params,
+        log_index: u64,
+    ) -> Self {
+        let threshold = params.threshold();
+
+        let status = match threshold {
+            Some(threshold) => VerdictStatus::decide(build, threshold),
+            None => VerdictStatus::InsufficientParticipation {
+                revealed: build.reveals.len(),
+            },
+        };
+
+        Self {
+            build_id,
+            request: build.request.clone(),
+            finalized_at: now,
+            threshold,
+            log_index,
+            status,
+        }
+    }
+
+    /// Look up the latest verdict on a crate version and target in the
+    /// registry with the given index URL (or crates.io if none)
+    pub fn lookup(
+        state_view: &dyn StateView,
+        registry: Option<&str>,
+        crate_name: &str,
+        version: &str,
+        target: &str,
+    ) -> Result<Option<Self>, Error> {
+        let path = state::verdict_path(registry, crate_name, version, target);
+
+        match state_view.get(&path)? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Decode a verdict from a leaf of the reproducibility log
+    pub fn from_log_leaf(leaf: &[u8]) -> Result<Self, bincode::Error> {
+        bincode::deserialize(leaf)
+    }
+
+    /// Encode this verdict as a leaf of the reproducibility log
+    pub fn to_log_leaf(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("verdict serialization failed")
+    }
+
+    /// Find the reproduced artifact with the given SHA-256 digest (if any)
+    pub fn find_artifact(&self, sha256: &Digest) -> Option<&ManifestEntry> {
+        match &self.status {
+            VerdictStatus::Reproduced { manifest, .. } => manifest.find(sha256),
+            _ => None,
+        }
+    }
+
+    /// Was the build reproduced?
+    pub fn is_reproduced(&self) -> bool {
+        match self.status {
+            VerdictStatus::Reproduced { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl VerdictStatus {
+    /// Group the revealed artifact roots (and the sources they were built
+    /// from) for a build and check them against the threshold
+    pub fn decide(build: &Build, threshold: usize) -> Self {
+        // Builders only agree if they built the same sources, so each reveal
+        // is paired with the source digest its builder committed to. Reveals
+        // whose sources weren't recorded can't be attributed to any sources,
+        // so they aren't counted.
+        let results: BTreeMap<AccountAddress, BuildResult> = build
+            .reveals
+            .iter()
+            .filter_map(|(builder, artifact_root)| {
+                build.sources.get(builder).map(|source_digest| {
+                    let result = BuildResult {
+                        source_digest: *source_digest,
+                        artifact_root: *artifact_root,
+                    };
+
+                    (*builder, result)
+                })
+            })
+            .collect();
+
+        if results.is_empty() || results.len() < threshold {
+            return VerdictStatus::InsufficientParticipation {
+                revealed: results.len(),
+            };
+        }
+
+        let mut groups: BTreeMap<BuildResult, BTreeSet<AccountAddress>> = BTreeMap::new();
+
+        for (builder, result) in &results {
+            groups.entry(*result).or_default().insert(*builder);
+        }
+
+        // Ties are broken by the smallest result so every node decides the
+        // same way
+        let (plurality, builders) = groups
+            .into_iter()
+            .fold(
+                None,
+                |best: Option<(BuildResult, BTreeSet<_>)>, (result, builders)| match best {
+                    Some(best) if best.1.len() >= builders.len() => Some(best),
+                    _ => Some((result, builders)),
+                },
+            )
+            .expect("results should be non-empty");
+
+        let dissenters = results
+            .into_iter()
+            .filter(|(_, result)| *result != plurality)
+            .collect();
+
+        let BuildResult {
+            source_digest,
+            artifact_root: plurality_root,
+        } = plurality;
+
+        // A revealed root without a manifest is a bug, but halting every
+        // validator over it would be worse: the build isn't reproduced then
+        match build.manifests.get(&plurality_root) {
+            Some(manifest) if builders.len() >= threshold => VerdictStatus::Reproduced {
+                source_digest,
+                artifact_root: plurality_root,
+                manifest: manifest.clone(),
+                builders,
+                dissenters,
+            },
+            _ => VerdictStatus::Diverged {
+                source_digest,
+                plurality_root,
+                dissenters,
+            },
+        }
+    }
+}
@@ -5,8 +5,9 @@ use std::sync::Arc;
 use synchro::{
     error::Error,
     futures::future::Future,
-    storage_client::StorageRead,
-    transaction::{NewVerifier, SignedTransaction, Status, TransactionValidation},
+    scratchpad::SparseMerkleTree,
+    storage_client::{StorageRead, VerifiedStateView},
+    transaction::{NewVerifier, SignedTransaction, Status, TransactionValidation, VMVerifier},
 };
 
 /// Verification provider
@@ -30,15 +31,14 @@ impl NewVerifier for VerifyProvider {
 }
 
 /// Validator for Synchronicity transactions
-#[allow(dead_code)] // TODO(tarcieri): verify stuff
 pub struct Verifier {
     storage_read_client: Arc<dyn StorageRead>,
     executor: SynchronicityExecutor,
 }
 
 impl Verifier {
+    /// Create a new verifier which reads state from the given storage client
     pub fn new(storage_read_client: Arc<dyn StorageRead>) -> Self {
-        // TODO(tarcieri): make this real
         let executor = SynchronicityExecutor::new();
 
         Self {
@@ -56,8 +56,25 @@ impl TransactionValidation for Verifier {
     /// <https://github.com/libra/libra/blob/testnet/vm-validator/src/vm_validator.rs#L47>
     fn validate_transaction(
         &self,
-        _txn: SignedTransaction,
+        txn: SignedTransaction,
     ) -> Box<dyn Future<Item = Option<Status>, Error = Error> + Send> {
-        unimplemented!();
+        let storage_read_client = Arc::clone(&self.storage_read_client);
+        let executor = self.executor.clone();
+
+        Box::new(
+            self.storage_read_client
+                .get_latest_state_root_async()
+                .and_then(move |(version, state_root)| {
+                    let smt = SparseMerkleTree::new(state_root);
+                    let state_view = VerifiedStateView::new(
+                        storage_read_client,
+                        Some(version),
+                        state_root,
+                        &smt,
+                    );
+
+                    Ok(executor.validate_transaction(txn, &state_view))
+                }),
+        )
     }
 }
@@ -0,0 +1,445 @@
+//! crates.io index watcher: follows a local copy of the crates.io index (or
+//! an alternative registry's index) and automatically submits build requests
+//! for newly published crate versions which match a set of patterns.
+//!
+//! When several builders run the watcher, each new version is assigned a
+//! submission order among them derived from the build ID. Builders wait for
+//! their turn and only submit if the build hasn't been requested by the time
+//! it comes, so normally only one request per version reaches mempool.
+
+mod index;
+
+pub use self::index::Index;
+
+use crate::{
+    config::WatcherConfig,
+    error::{Error, ErrorKind},
+    participant::Ledger,
+    payload::{BuildEnv, BuildRequest, Payload},
+    prelude::*,
+    registry::{IndexEntry, Registry},
+    state::BuildId,
+};
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// File in the scratch directory where versions the watcher has dealt with
+/// are recorded, one `<name> <version>` per line
+pub const SEEN_FILE: &str = "index-watcher.txt";
+
+/// How often to scan the index by default
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long each builder waits for the previous one in the submission order
+/// by default
+pub const DEFAULT_SUBMIT_STAGGER: Duration = Duration::from_secs(60);
+
+/// Which crates to build, as a list of names or glob patterns (where `*`
+/// matches any sequence of characters and `?` matches any single character)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CrateFilter {
+    /// Patterns to match crate names against
+    patterns: Vec<String>,
+}
+
+impl CrateFilter {
+    /// Create a filter from a list of patterns
+    pub fn new(patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Does the given crate name match any of the patterns?
+    ///
+    /// Crate names are compared case-insensitively, as on crates.io.
+    pub fn matches(&self, crate_name: &str) -> bool {
+        let crate_name = crate_name.to_ascii_lowercase();
+
+        self.patterns.iter().any(|pattern| {
+            glob_match(
+                pattern.to_ascii_lowercase().as_bytes(),
+                crate_name.as_bytes(),
+            )
+        })
+    }
+}
+
+/// Build request waiting for its turn to be submitted
+struct Queued {
+    /// Request to submit
+    request: BuildRequest,
+
+    /// Time at which this builder should submit it
+    due: Instant,
+}
+
+/// Watches a crates.io index and submits build requests for new versions
+pub struct IndexWatcher<L: Ledger> {
+    /// Ledger to submit build requests through
+    ledger: L,
+
+    /// Local copy of the index
+    index: Index,
+
+    /// Crates to build
+    filter: CrateFilter,
+
+    /// Index URL of the registry being watched (crates.io if unset)
+    registry: Option<String>,
+
+    /// Environment to request builds in
+    env: BuildEnv,
+
+    /// Update the index with `git` before each scan
+    fetch: bool,
+
+    /// How long to wait for each builder earlier in the submission order
+    stagger: Duration,
+
+    /// Where `seen` is persisted
+    seen_file: PathBuf,
+
+    /// Matching versions which have been submitted or found on the ledger,
+    /// as `(name, version)`
+    seen: BTreeSet<(String, String)>,
+
+    /// Build requests waiting for their turn
+    queue: BTreeMap<BuildId, Queued>,
+
+    /// Has `seen` been loaded (or the index baselined)?
+    initialized: bool,
+}
+
+impl<L: Ledger> IndexWatcher<L> {
+    /// Create a new watcher which records its progress in the given scratch
+    /// directory
+    pub fn new(
+        ledger: L,
+        index: Index,
+        filter: CrateFilter,
+        env: BuildEnv,
+        scratch_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            ledger,
+            index,
+            filter,
+            registry: None,
+            env,
+            fetch: false,
+            stagger: DEFAULT_SUBMIT_STAGGER,
+            seen_file: scratch_dir.into().join(SEEN_FILE),
+            seen: BTreeSet::new(),
+            queue: BTreeMap::new(),
+            initialized: false,
+        }
+    }
+
+    /// Create a watcher from its configuration
+    pub fn from_config(
+        ledger: L,
+        config: &WatcherConfig,
+        scratch_dir: impl Into<PathBuf>,
+    ) -> Result<Self, Error> {
+        config.env.validate()?;
+        Registry::from_index_url(config.registry.as_ref().map(String::as_str))?;
+
+        let index = Index::open(&config.index_dir)?;
+        let fetch = config.fetch && index.is_git();
+
+        Ok(Self::new(
+            ledger,
+            index,
+            CrateFilter::new(&config.crates),
+            config.env.clone(),
+            scratch_dir,
+        )
+        .with_registry(config.registry.clone())
+        .with_fetch(fetch)
+        .with_stagger(config.submit_stagger()))
+    }
+
+    /// Set the index URL of the registry being watched (crates.io if unset)
+    pub fn with_registry(mut self, registry: Option<String>) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Set whether to update a git checkout of the index before each scan
+    pub fn with_fetch(mut self, fetch: bool) -> Self {
+        self.fetch = fetch;
+        self
+    }
+
+    /// Set how long to wait for each builder earlier in the submission order
+    pub fn with_stagger(mut self, stagger: Duration) -> Self {
+        self.stagger = stagger;
+        self
+    }
+
+    /// Get the ledger this watcher is using
+    pub fn ledger(&self) -> &L {
+        &self.ledger
+    }
+
+    /// Watch the index forever, scanning it at the given interval
+    pub fn run(&mut self, poll_interval: Duration) -> ! {
+        loop {
+            if let Err(e) = self.step() {
+                status_warn!("error watching crates.io index: {}", e);
+            }
+
+            thread::sleep(poll_interval);
+        }
+    }
+
+    /// Scan the index for new versions, then submit build requests which are
+    /// due and haven't already been requested
+    pub fn step(&mut self) -> Result<(), Error> {
+        if self.fetch {
+            if let Err(e) = self.index.fetch() {
+                status_warn!("couldn't update crates.io index: {}", e);
+            }
+        }
+
+        let baseline = !self.initialized && !self.seen_file.exists();
+
+        if !self.initialized && !baseline {
+            self.load_seen()?;
+            self.initialized = true;
+        }
+
+        let entries = self.index.changed_entries()?;
+
+        // The first time the watcher runs, everything already published is
+        // considered seen, so only versions published from now on are built
+        if baseline {
+            for entry in entries {
+                if self.filter.matches(&entry.name) {
+                    self.seen.insert((entry.name, entry.vers));
+                }
+            }
+
+            self.save_seen()?;
+            self.initialized = true;
+            return Ok(());
+        }
+
+        for entry in entries {
+            self.enqueue(entry)?;
+        }
+
+        self.submit_due();
+        Ok(())
+    }
+
+    /// Queue a build request for an index entry if it's new and matches
+    fn enqueue(&mut self, entry: IndexEntry) -> Result<(), Error> {
+        if entry.yanked || !self.filter.matches(&entry.name) {
+            return Ok(());
+        }
+
+        if self
+            .seen
+            .contains(&(entry.name.clone(), entry.vers.clone()))
+        {
+            return Ok(());
+        }
+
+        let request = BuildRequest {
+            crate_name: entry.name,
+            version: entry.vers,
+            registry: self.registry.clone(),
+            env: self.env.clone(),
+        };
+
+        if let Err(e) = request.validate() {
+            status_warn!(
+                "not building {} {}: {}",
+                request.crate_name,
+                request.version,
+                e
+            );
+            return self.mark_seen(&request);
+        }
+
+        let build_id = request.id();
+
+        if !self.queue.contains_key(&build_id) {
+            let due = Instant::now() + self.submit_delay(&build_id)?;
+            self.queue.insert(build_id, Queued { request, due });
+        }
+
+        Ok(())
+    }
+
+    /// Submit queued requests whose turn has come, unless another builder
+    /// already has.
+    ///
+    /// A request which can't be submitted is logged and stays queued, to be
+    /// retried after the next scan, without holding up the rest.
+    fn submit_due(&mut self) {
+        let now = Instant::now();
+
+        let due: Vec<BuildId> = self
+            .queue
+            .iter()
+            .filter(|(_, queued)| queued.due <= now)
+            .map(|(build_id, _)| *build_id)
+            .collect();
+
+        for build_id in due {
+            let request = self.queue[&build_id].request.clone();
+
+            if let Err(e) = self.submit_unless_requested(&build_id, &request) {
+                status_warn!(
+                    "couldn't submit {} {} ({}): {}",
+                    request.crate_name,
+                    request.version,
+                    request.env.target,
+                    e
+                );
+                continue;
+            }
+
+            self.queue.remove(&build_id);
+
+            if let Err(e) = self.mark_seen(&request) {
+                status_warn!(
+                    "couldn't record {} {} as seen: {}",
+                    request.crate_name,
+                    request.version,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Submit a build request, unless the build has already been requested
+    fn submit_unless_requested(
+        &self,
+        build_id: &BuildId,
+        request: &BuildRequest,
+    ) -> Result<(), Error> {
+        if self.ledger.build(build_id)?.is_some() {
+            return Ok(());
+        }
+
+        self.ledger
+            .submit(&Payload::BuildRequest(request.clone()))?;
+
+        status_ok!(
+            "Submitted",
+            "{} {} ({}) as build {}",
+            request.crate_name,
+            request.version,
+            request.env.target,
+            build_id
+        );
+
+        Ok(())
+    }
+
+    /// How long to wait before submitting a build request.
+    ///
+    /// Builders are ordered by the hash of the build ID and their address,
+    /// and each waits one stagger interval per builder ahead of it. Accounts
+    /// which aren't in the builder set go last.
+    fn submit_delay(&self, build_id: &BuildId) -> Result<Duration, Error> {
+        let sender = self.ledger.sender();
+        let params = self.ledger.params()?;
+
+        let rank = if params.is_builder(&sender) {
+            let own_priority = submit_priority(build_id, sender.as_ref());
+
+            params
+                .builders
+                .iter()
+                .filter(|builder| submit_priority(build_id, builder.as_ref()) < own_priority)
+                .count()
+        } else {
+            params.builders.len()
+        };
+
+        Ok(self.stagger * rank as u32)
+    }
+
+    /// Record that a version has been dealt with
+    fn mark_seen(&mut self, request: &BuildRequest) -> Result<(), Error> {
+        let newly_seen = self
+            .seen
+            .insert((request.crate_name.clone(), request.version.clone()));
+
+        if !newly_seen {
+            return Ok(());
+        }
+
+        // Append rather than rewriting the whole file for every version
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.seen_file)
+            .map_err(|e| ErrorKind::Io.context(e))?;
+
+        writeln!(file, "{} {}", request.crate_name, request.version)
+            .map_err(|e| ErrorKind::Io.context(e).into())
+    }
+
+    /// Load the versions which have been dealt with
+    fn load_seen(&mut self) -> Result<(), Error> {
+        let contents = fs::read_to_string(&self.seen_file).map_err(|e| ErrorKind::Io.context(e))?;
+
+        for line in contents.lines() {
+            let mut fields = line.splitn(2, ' ');
+
+            if let (Some(name), Some(version)) = (fields.next(), fields.next()) {
+                self.seen.insert((name.to_owned(), version.to_owned()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Persist all of the versions which have been dealt with, replacing the
+    /// file atomically so a crash can't leave it half-written
+    fn save_seen(&self) -> Result<(), Error> {
+        let contents: String = self
+            .seen
+            .iter()
+            .map(|(name, version)| format!("{} {}\n", name, version))
+            .collect();
+
+        let tmp_file = self.seen_file.with_extension("tmp");
+        fs::write(&tmp_file, contents).map_err(|e| ErrorKind::Io.context(e))?;
+        fs::rename(&tmp_file, &self.seen_file).map_err(|e| ErrorKind::Io.context(e).into())
+    }
+}
+
+/// Priority of a builder for submitting a build request (lower goes first)
+fn submit_priority(build_id: &BuildId, builder: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input(&build_id.0);
+    hasher.input(builder);
+
+    let mut priority = [0u8; 32];
+    priority.copy_from_slice(&hasher.result());
+    priority
+}
+
+/// Match a name against a glob pattern supporting `*` and `?`
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| glob_match(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && glob_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && glob_match(rest, &name[1..]),
+    }
+}
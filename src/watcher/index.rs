@@ -0,0 +1,121 @@
+//! Reading a crates.io index: either a git checkout of
+//! <https://github.com/rust-lang/crates.io-index> or a plain directory with
+//! the same layout (e.g. a local mirror)
+
+use crate::{
+    error::{Error, ErrorKind},
+    prelude::*,
+    registry::{parse_entries, IndexEntry, CONFIG_FILE},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+/// Local copy of a crates.io index
+pub struct Index {
+    /// Root directory of the index
+    dir: PathBuf,
+
+    /// Time the previous scan started (if any)
+    last_scan: Option<SystemTime>,
+}
+
+impl Index {
+    /// Open the index in the given directory
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let dir = dir.into();
+
+        if !dir.is_dir() {
+            fail!(
+                ErrorKind::Config,
+                "crates.io index not found: {}",
+                dir.display()
+            );
+        }
+
+        Ok(Self {
+            dir,
+            last_scan: None,
+        })
+    }
+
+    /// Is the index a git checkout?
+    pub fn is_git(&self) -> bool {
+        self.dir.join(".git").exists()
+    }
+
+    /// Update a git checkout to the latest upstream commit.
+    ///
+    /// The upstream index is occasionally squashed, so the checkout is reset
+    /// to the fetched commit rather than fast-forwarded.
+    pub fn fetch(&self) -> Result<(), Error> {
+        git(&self.dir, &["fetch", "--quiet", "origin"])?;
+        git(&self.dir, &["reset", "--quiet", "--hard", "FETCH_HEAD"])
+    }
+
+    /// Read the entries in every file which changed since the previous scan
+    /// (or every file, on the first scan).
+    ///
+    /// Entries may be returned more than once, so callers must deduplicate.
+    pub fn changed_entries(&mut self) -> Result<Vec<IndexEntry>, Error> {
+        let scan_start = SystemTime::now();
+        let mut entries = vec![];
+        read_dir_entries(&self.dir, self.last_scan, &mut entries)?;
+        self.last_scan = Some(scan_start);
+        Ok(entries)
+    }
+}
+
+/// Read entries from the files in a directory (recursively) which were
+/// modified at or after the given time
+fn read_dir_entries(
+    dir: &Path,
+    since: Option<SystemTime>,
+    entries: &mut Vec<IndexEntry>,
+) -> Result<(), Error> {
+    for dir_entry in fs::read_dir(dir).map_err(|e| ErrorKind::Io.context(e))? {
+        let dir_entry = dir_entry.map_err(|e| ErrorKind::Io.context(e))?;
+        let metadata = dir_entry.metadata().map_err(|e| ErrorKind::Io.context(e))?;
+        let name = dir_entry.file_name();
+
+        // Skip `.git` and the index configuration, which isn't a crate
+        if name.to_string_lossy().starts_with('.') || name == CONFIG_FILE {
+            continue;
+        }
+
+        if metadata.is_dir() {
+            read_dir_entries(&dir_entry.path(), since, entries)?;
+            continue;
+        }
+
+        let modified = metadata.modified().map_err(|e| ErrorKind::Io.context(e))?;
+
+        if since.map(|since| modified >= since).unwrap_or(true) {
+            let contents =
+                fs::read_to_string(dir_entry.path()).map_err(|e| ErrorKind::Io.context(e))?;
+
+            entries.extend(parse_entries(&contents));
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `git` in the given directory
+fn git(dir: &Path, args: &[&str]) -> Result<(), Error> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .status()
+        .map_err(|e| ErrorKind::Io.context(e))?;
+
+    if !status.success() {
+        fail!(ErrorKind::Io, "git {} failed: {}", args.join(" "), status);
+    }
+
+    Ok(())
+}
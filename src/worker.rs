@@ -0,0 +1,282 @@
+//! Build worker: fetches and verifies requested crates, builds them with a
+//! `BuildBackend` under the scratch directory, and collects the resulting
+//! artifacts for hashing
+
+use crate::{
+    backend::{crate_file_name, BuildBackend, DockerBackend},
+    config::SynchronicityConfig,
+    error::{Error, ErrorKind},
+    manifest::{self, Manifest},
+    payload::{BuildRequest, Commit, Reveal},
+    prelude::*,
+    state::{BuildId, Digest},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use synchro::types::account_address::AccountAddress;
+
+/// Subdirectory of the scratch directory where builds are performed
+pub const BUILDS_DIR: &str = "builds";
+
+/// Subdirectory of a build directory where artifacts are collected
+pub const ARTIFACTS_DIR: &str = "artifacts";
+
+/// File in a build directory holding the encoded artifact manifest
+pub const MANIFEST_FILE: &str = "manifest.txt";
+
+/// File in a build directory holding the reveal for a commitment which has
+/// been made, so it can be opened even if the node restarts
+pub const REVEAL_FILE: &str = "reveal.bin";
+
+/// File extensions of build outputs which are collected as artifacts
+pub const ARTIFACT_EXTENSIONS: &[&str] = &["a", "dll", "dylib", "exe", "lib", "rlib", "so", "wasm"];
+
+/// How long build directories are retained by default (7 days)
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Result of building a crate
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildOutput {
+    /// SHA-256 digest of the `.crate` tarball which was built
+    pub source_digest: Digest,
+
+    /// Manifest of the build artifacts
+    pub manifest: Manifest,
+}
+
+/// Build worker
+pub struct Worker {
+    /// Scratch directory builds are performed in
+    scratch_dir: PathBuf,
+
+    /// Backend which performs builds
+    backend: Box<dyn BuildBackend>,
+
+    /// How long build directories are retained after they were last modified
+    retention: Duration,
+}
+
+impl Worker {
+    /// Create a new worker which builds inside of the given scratch directory
+    pub fn new(scratch_dir: impl Into<PathBuf>, backend: Box<dyn BuildBackend>) -> Self {
+        Self {
+            scratch_dir: scratch_dir.into(),
+            backend,
+            retention: DEFAULT_RETENTION,
+        }
+    }
+
+    /// Create a worker which builds inside of Docker using Rustwide
+    pub fn from_config(config: &SynchronicityConfig) -> Result<Self, Error> {
+        let backend = DockerBackend::init(&config.scratch_dir)?;
+        Ok(Self::new(&config.scratch_dir, Box::new(backend))
+            .with_retention(config.artifacts.retention()))
+    }
+
+    /// Set how long build directories are retained
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Get the directory a build is performed in
+    pub fn build_dir(&self, request: &BuildRequest) -> PathBuf {
+        build_dir(&self.scratch_dir, &request.id())
+    }
+
+    /// Remove build directories which are older than the retention period,
+    /// returning how many were removed
+    pub fn prune(&self) -> Result<usize, Error> {
+        let builds_dir = self.scratch_dir.join(BUILDS_DIR);
+
+        if !builds_dir.exists() {
+            return Ok(0);
+        }
+
+        let now = SystemTime::now();
+        let mut pruned = 0;
+
+        for entry in fs::read_dir(&builds_dir).map_err(|e| ErrorKind::Io.context(e))? {
+            let entry = entry.map_err(|e| ErrorKind::Io.context(e))?;
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map_err(|e| ErrorKind::Io.context(e))?;
+
+            // Timestamps in the future are treated as brand new
+            let age = now.duration_since(modified).unwrap_or_default();
+
+            if age > self.retention {
+                fs::remove_dir_all(entry.path()).map_err(|e| ErrorKind::Io.context(e))?;
+                pruned += 1;
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    /// Build the requested crate, returning the digest of its sources and the
+    /// manifest of its artifacts.
+    ///
+    /// Fails with `ErrorKind::Checksum` if the `.crate` tarball fetched
+    /// doesn't match the registry index.
+    pub fn build(&self, request: &BuildRequest) -> Result<BuildOutput, Error> {
+        request.validate()?;
+
+        // Refuse to build unless the environment can be honored exactly
+        self.backend.check_env(&request.env)?;
+
+        let build_dir = self.build_dir(request);
+
+        // Always start from a clean build directory
+        if build_dir.exists() {
+            fs::remove_dir_all(&build_dir).map_err(|e| ErrorKind::Io.context(e))?;
+        }
+
+        fs::create_dir_all(&build_dir).map_err(|e| ErrorKind::Io.context(e))?;
+
+        let crate_file = build_dir.join(crate_file_name(request));
+        let source_digest = self.backend.registry(request)?.download(
+            &request.crate_name,
+            &request.version,
+            &crate_file,
+        )?;
+
+        let output_dir = self.backend.build(request, &crate_file, &build_dir)?;
+        let artifacts_dir = build_dir.join(ARTIFACTS_DIR);
+        collect_artifacts(&output_dir, &artifacts_dir)?;
+
+        // The sources are covered by the manifest too, so the `.crate` which
+        // was built can be checked against a verdict like any other artifact
+        fs::copy(&crate_file, artifacts_dir.join(crate_file_name(request)))
+            .map_err(|e| ErrorKind::Io.context(e))?;
+
+        let manifest = Manifest::from_dir(&artifacts_dir)?;
+        fs::write(build_dir.join(MANIFEST_FILE), manifest.to_string())
+            .map_err(|e| ErrorKind::Io.context(e))?;
+
+        Ok(BuildOutput {
+            source_digest,
+            manifest,
+        })
+    }
+
+    /// Build the requested crate and commit to its artifacts on behalf of
+    /// the given builder, saving the corresponding reveal.
+    ///
+    /// If a reveal was already saved, the build isn't repeated: a commitment
+    /// to it may still be waiting in mempool (e.g. across a restart), and
+    /// rebuilding would replace the reveal needed to open it.
+    pub fn commit(
+        &self,
+        request: &BuildRequest,
+        builder: &AccountAddress,
+    ) -> Result<Commit, Error> {
+        if let Some(reveal) = self.reveal(request)? {
+            // The manifest covers the `.crate` which was built
+            let source_digest = match reveal
+                .manifest
+                .entries
+                .iter()
+                .find(|entry| entry.path == crate_file_name(request))
+            {
+                Some(entry) => entry.sha256,
+                None => fail!(
+                    ErrorKind::Build,
+                    "saved reveal for build {} doesn't cover its sources",
+                    reveal.build_id
+                ),
+            };
+
+            return Ok(Commit {
+                build_id: reveal.build_id,
+                source_digest,
+                commitment: reveal.commitment(builder, &source_digest),
+            });
+        }
+
+        let output = self.build(request)?;
+        let reveal = Reveal {
+            build_id: request.id(),
+            manifest: output.manifest,
+            salt: rand::random(),
+        };
+
+        let reveal_bytes = bincode::serialize(&reveal).map_err(|e| ErrorKind::Io.context(e))?;
+
+        fs::write(self.build_dir(request).join(REVEAL_FILE), reveal_bytes)
+            .map_err(|e| ErrorKind::Io.context(e))?;
+
+        Ok(Commit {
+            build_id: reveal.build_id,
+            source_digest: output.source_digest,
+            commitment: reveal.commitment(builder, &output.source_digest),
+        })
+    }
+
+    /// Load the reveal saved when committing to a build (if any)
+    pub fn reveal(&self, request: &BuildRequest) -> Result<Option<Reveal>, Error> {
+        let reveal_path = self.build_dir(request).join(REVEAL_FILE);
+
+        if !reveal_path.exists() {
+            return Ok(None);
+        }
+
+        let reveal_bytes = fs::read(&reveal_path).map_err(|e| ErrorKind::Io.context(e))?;
+        let reveal = bincode::deserialize(&reveal_bytes).map_err(|e| ErrorKind::Io.context(e))?;
+        Ok(Some(reveal))
+    }
+}
+
+/// Get the directory a build is performed (and its artifacts retained) in,
+/// within the given scratch directory
+pub fn build_dir(scratch_dir: &Path, build_id: &BuildId) -> PathBuf {
+    scratch_dir.join(BUILDS_DIR).join(build_id.to_string())
+}
+
+/// Copy artifacts from a build's output directory into the artifacts
+/// directory
+fn collect_artifacts(output_dir: &Path, artifacts_dir: &Path) -> Result<(), Error> {
+    fs::create_dir_all(artifacts_dir).map_err(|e| ErrorKind::Io.context(e))?;
+
+    let mut collected = 0;
+
+    for entry in fs::read_dir(output_dir).map_err(|e| ErrorKind::Io.context(e))? {
+        let entry = entry.map_err(|e| ErrorKind::Io.context(e))?;
+        let path = entry.path();
+
+        if is_artifact(&path)? {
+            fs::copy(&path, artifacts_dir.join(entry.file_name()))
+                .map_err(|e| ErrorKind::Io.context(e))?;
+
+            collected += 1;
+        }
+    }
+
+    if collected == 0 {
+        fail!(
+            ErrorKind::Build,
+            "build produced no artifacts in {}",
+            output_dir.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Is the file at the given path a build artifact?
+fn is_artifact(path: &Path) -> Result<bool, Error> {
+    let metadata = fs::metadata(path).map_err(|e| ErrorKind::Io.context(e))?;
+
+    if !metadata.is_file() {
+        return Ok(false);
+    }
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => Ok(ARTIFACT_EXTENSIONS.contains(&ext)),
+        None => Ok(manifest::is_executable(&metadata)),
+    }
+}
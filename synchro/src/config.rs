@@ -4,5 +4,9 @@ pub mod builder;
 pub mod key_seed;
 pub mod peer_info;
 
-pub use self::{builder::Builder, key_seed::KeySeed, peer_info::PeerInfo};
+pub use self::{
+    builder::Builder,
+    key_seed::KeySeed,
+    peer_info::{PeerInfo, PeerInfoSet},
+};
 pub use libra_config::{config::*, keys, seed_peers, trusted_peers, utils};
@@ -88,6 +88,12 @@ impl Builder {
         self
     }
 
+    /// Set the node's role
+    pub fn with_role(&mut self, role: RoleType) -> &mut Self {
+        self.role = role;
+        self
+    }
+
     /// Configure whether or not the network is permissioned
     pub fn with_is_permissioned(&mut self, is_permissioned: bool) -> &mut Self {
         // TODO(tarcieri): support permissionless networks
@@ -104,7 +110,11 @@ impl Builder {
         self
     }
 
-    /// Generate keys and initial configuration settings
+    /// Generate keys and initial configuration settings.
+    ///
+    /// The generated peer configs only contain this node: the configs for a
+    /// whole group are assembled from each node's `peer_info.toml` using
+    /// [`PeerInfoSet`](super::PeerInfoSet).
     pub fn generate_keys_and_configs(
         &self,
         domain: &[u8],
@@ -145,11 +155,7 @@ impl Builder {
         let mut network_config = NetworkConfig::default();
         network_config.peer_id = peer_id.to_owned();
 
-        network_config.role = match self.role {
-            RoleType::Validator => "validator",
-            RoleType::FullNode => "full_node",
-        }
-        .to_owned();
+        network_config.role = peer_info::role_name(self.role).to_owned();
 
         network_config.listen_address = self.listen_address.clone();
         network_config.advertised_address = self.advertised_address.clone();
@@ -197,6 +203,7 @@ impl Builder {
         let network_info = network_peers.peers.into_iter().next().unwrap().1;
         let peer_info = PeerInfo::new(
             peer_id,
+            self.role,
             self.seed_address.as_ref(),
             consensus_info,
             network_info,
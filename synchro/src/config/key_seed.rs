@@ -2,6 +2,7 @@
 
 use hkd32::mnemonic;
 use std::convert::TryInto;
+use thiserror::Error;
 
 /// Toplevel path component for personalizing all `synchro`-derived subkeys
 pub const TOPLEVEL_DERIVATION_COMPONENT: &[u8] = b"synchro";
@@ -16,6 +17,17 @@ impl KeySeed {
         KeySeed(mnemonic::Phrase::random(Default::default()))
     }
 
+    /// Restore a KeySeed from a previously generated (English) BIP39 phrase.
+    ///
+    /// Words may be separated by any amount of whitespace.
+    pub fn from_phrase(phrase: &str) -> Result<Self, InvalidPhrase> {
+        let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        mnemonic::Phrase::new(phrase, Default::default())
+            .map(KeySeed)
+            .map_err(|_| InvalidPhrase)
+    }
+
     /// Get the phrase for this `KeySeed` as a string
     pub fn phrase(&self) -> &str {
         self.0.phrase()
@@ -43,3 +55,9 @@ impl KeySeed {
             .unwrap()
     }
 }
+
+/// Error returned when a BIP39 phrase is malformed (e.g. unknown words, the
+/// wrong number of words, or a bad checksum)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Error)]
+#[error("invalid BIP39 phrase")]
+pub struct InvalidPhrase;
@@ -1,11 +1,34 @@
 //! `peer_info.toml` files used to build devnet genesis/configuration
 
-use libra_config::trusted_peers::{ConsensusPeerInfo, NetworkPeerInfo};
+use libra_config::{
+    config::RoleType,
+    seed_peers::SeedPeersConfig,
+    trusted_peers::{ConsensusPeerInfo, ConsensusPeersConfig, NetworkPeerInfo, NetworkPeersConfig},
+};
+use parity_multiaddr::Multiaddr;
 use serde::{Deserialize, Serialize};
 
 /// Name of the `PeerInfo` file
 pub const DEFAULT_FILENAME: &str = "peer_info.toml";
 
+/// Name of the file containing the `PeerInfo` of every validator (but no
+/// full nodes) in a group
+pub const VALIDATORS_FILENAME: &str = "validators.toml";
+
+/// Name of the validator role in configuration files
+pub const VALIDATOR_ROLE: &str = "validator";
+
+/// Name of the full node role in configuration files
+pub const FULL_NODE_ROLE: &str = "full_node";
+
+/// Get the name of a role as used in configuration files
+pub fn role_name(role: RoleType) -> &'static str {
+    match role {
+        RoleType::Validator => VALIDATOR_ROLE,
+        RoleType::FullNode => FULL_NODE_ROLE,
+    }
+}
+
 /// Public keys for a particular network peer
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PeerInfo {
@@ -24,6 +47,10 @@ pub struct PeerInfo {
     #[serde(default)]
     pub logo_url: String,
 
+    /// Role of this peer (`validator` or `full_node`)
+    #[serde(default = "default_role")]
+    pub role: String,
+
     /// Seed IP address to include in `peer_info.toml`
     pub seed_ip: Option<String>,
 
@@ -35,9 +62,11 @@ pub struct PeerInfo {
 }
 
 impl PeerInfo {
-    /// Create new `PeerInfo` from the given peer ID, consensus and network peer info
+    /// Create new `PeerInfo` from the given peer ID, role, consensus and
+    /// network peer info
     pub fn new(
         peer_id: impl ToString,
+        role: RoleType,
         seed_ip: Option<impl ToString>,
         consensus: ConsensusPeerInfo,
         network: NetworkPeerInfo,
@@ -47,9 +76,91 @@ impl PeerInfo {
             description: Default::default(),
             website_url: Default::default(),
             logo_url: Default::default(),
+            role: role_name(role).to_owned(),
             seed_ip: seed_ip.map(|ip| ip.to_string()),
             consensus,
             network,
         }
     }
+
+    /// Is this peer a validator (as opposed to a full node)?
+    pub fn is_validator(&self) -> bool {
+        self.role == VALIDATOR_ROLE
+    }
+}
+
+/// Peers which don't specify a role are validators
+fn default_role() -> String {
+    VALIDATOR_ROLE.to_owned()
+}
+
+/// Collection of `PeerInfo` for peers in a group, validators and full nodes
+/// alike (`validators.toml` is the subset containing only validators)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PeerInfoSet {
+    /// Peers in the group
+    #[serde(default, rename = "peer")]
+    pub peers: Vec<PeerInfo>,
+}
+
+impl PeerInfoSet {
+    /// Create a set from the given peers, sorted by peer ID so every node
+    /// derives identical configuration from the same peers
+    pub fn new(mut peers: Vec<PeerInfo>) -> Self {
+        peers.sort_by(|a, b| a.id.cmp(&b.id));
+        Self { peers }
+    }
+
+    /// Get the peer with the given ID (if any)
+    pub fn get(&self, peer_id: &str) -> Option<&PeerInfo> {
+        self.peers.iter().find(|peer| peer.id == peer_id)
+    }
+
+    /// Validators in the set (i.e. every peer which isn't a full node)
+    pub fn validators(&self) -> impl Iterator<Item = &PeerInfo> {
+        self.peers.iter().filter(|peer| peer.is_validator())
+    }
+
+    /// Consensus public keys of every validator in the set
+    pub fn consensus_peers(&self) -> ConsensusPeersConfig {
+        ConsensusPeersConfig {
+            peers: self
+                .validators()
+                .map(|peer| (peer.id.clone(), peer.consensus.clone()))
+                .collect(),
+        }
+    }
+
+    /// Network public keys of every peer in the set, including full nodes
+    pub fn network_peers(&self) -> NetworkPeersConfig {
+        NetworkPeersConfig {
+            peers: self
+                .peers
+                .iter()
+                .map(|peer| (peer.id.clone(), peer.network.clone()))
+                .collect(),
+        }
+    }
+
+    /// Seed addresses of every peer in the set (including full nodes) which
+    /// has one, other than the given peer (i.e. the node being configured)
+    pub fn seed_peers(
+        &self,
+        own_peer_id: &str,
+    ) -> Result<SeedPeersConfig, parity_multiaddr::Error> {
+        let mut seed_peers = SeedPeersConfig::default();
+
+        for peer in &self.peers {
+            if peer.id == own_peer_id {
+                continue;
+            }
+
+            if let Some(seed_ip) = &peer.seed_ip {
+                let addr: Multiaddr = seed_ip.parse()?;
+                seed_peers.seed_peers.insert(peer.id.clone(), vec![addr]);
+            }
+        }
+
+        Ok(seed_peers)
+    }
 }
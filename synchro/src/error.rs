@@ -1,3 +1,65 @@
 //! Error types
 
 pub use libra_failure_ext::{Error, Result};
+
+use thiserror::Error;
+
+/// Errors which occur when launching a node
+#[derive(Debug, Error)]
+pub enum LaunchError {
+    /// The node config doesn't contain exactly one network config
+    #[error("expected exactly one network config, found {0}")]
+    NetworkConfig(usize),
+
+    /// A peer ID in the node config is malformed
+    #[error("invalid peer ID {peer_id:?}: {reason}")]
+    InvalidPeerId {
+        /// Peer ID as it appears in the config
+        peer_id: String,
+
+        /// Why it couldn't be parsed
+        reason: String,
+    },
+
+    /// A required key is missing from the node config
+    #[error("missing {0}")]
+    MissingKey(&'static str),
+
+    /// Couldn't bind a service to its configured address
+    #[error("couldn't bind {addr}: {reason}")]
+    Bind {
+        /// Address the service was configured to listen on
+        addr: String,
+
+        /// Underlying error
+        reason: String,
+    },
+
+    /// Couldn't connect to the storage service
+    #[error("couldn't connect to storage at {addr}: {reason}")]
+    Storage {
+        /// Address of the storage service
+        addr: String,
+
+        /// Underlying error
+        reason: String,
+    },
+
+    /// Couldn't initialize the Tokio runtime
+    #[error("couldn't initialize Tokio runtime: {0}")]
+    Runtime(#[from] std::io::Error),
+
+    /// Consensus failed to start
+    #[error("couldn't start consensus: {0}")]
+    Consensus(String),
+}
+
+impl LaunchError {
+    /// Create an `InvalidPeerId` error
+    pub fn invalid_peer_id(peer_id: impl ToString, reason: impl ToString) -> Self {
+        LaunchError::InvalidPeerId {
+            peer_id: peer_id.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
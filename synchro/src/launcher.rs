@@ -1,8 +1,8 @@
 //! Launcher - starts a node with the given configuration an executor
 
-use crate::{error::Error, node::Node, transaction::NewVerifier};
+use crate::{error::LaunchError, node::Node, storage::StorageRuntime, transaction::NewVerifier};
 use std::{
-    convert::TryInto,
+    convert::TryFrom,
     str::FromStr,
     sync::{Arc, Mutex},
     time::Instant,
@@ -25,6 +25,8 @@ use network::{
         ConsensusNetworkEvents,
         ConsensusNetworkSender,
         LibraNetworkProvider,
+        StateSynchronizerEvents,
+        StateSynchronizerSender,
         // when you add a new protocol const, you must add this in either
         // .direct_send_protocols or .rpc_protocols vector of network_builder in setup_network()
         ADMISSION_CONTROL_RPC_PROTOCOL,
@@ -60,33 +62,34 @@ where
     V: NewVerifier,
 {
     /// Create a new launcher
-    pub fn new(node_config: NodeConfig, verify_provider: V) -> Result<Self, Error> {
-        if let Some(net_config) = node_config.networks.get(0) {
-            let peer_id = PeerId::from_hex_literal(&net_config.peer_id)?;
-            let role = RoleType::from(&net_config.role);
-
-            Ok(Self {
-                node_config,
-                peer_id,
-                role,
-                verify_provider,
-            })
-        } else {
-            // TODO(tarcieri): don't panic!
-            panic!(
-                "unexpected number of network configs: {} (expected 1)",
-                node_config.networks.len()
-            );
+    pub fn new(node_config: NodeConfig, verify_provider: V) -> Result<Self, LaunchError> {
+        if node_config.networks.len() != 1 {
+            return Err(LaunchError::NetworkConfig(node_config.networks.len()));
         }
+
+        let net_config = &node_config.networks[0];
+        let peer_id = PeerId::from_hex_literal(&net_config.peer_id)
+            .map_err(|e| LaunchError::invalid_peer_id(&net_config.peer_id, e))?;
+        let role = RoleType::from(&net_config.role);
+
+        Ok(Self {
+            node_config,
+            peer_id,
+            role,
+            verify_provider,
+        })
     }
 
     /// Launch the node
-    pub fn launch<E>(mut self) -> Result<Node<E>, Error>
+    pub fn launch<E>(mut self) -> Result<Node<E>, LaunchError>
     where
         E: VMExecutor + Send + Sync + 'static,
     {
-        let runtime = crate::start_runtime();
-        let mut network_provider = self.start_network_provider(&runtime);
+        let runtime = crate::start_runtime()?;
+
+        // Storage must be up before anything which connects to it
+        let storage = StorageRuntime::start(&self.node_config)?;
+        let mut network_provider = self.start_network_provider(&runtime)?;
 
         // Note: We need to start network provider before consensus, because the consensus
         // initialization is blocked on state synchronizer to sync to the initial root ledger
@@ -97,42 +100,70 @@ where
         // network provider -> consensus -> state synchronizer -> network provider. This deadlock
         // was observed in GitHub Issue #749. A long term fix might be make
         // consensus initialization async instead of blocking on state synchronizer.
-        let mempool = self.start_mempool(network_provider.as_mut());
+        let mempool = self.start_mempool(network_provider.as_mut())?;
 
-        let (consensus_network_sender, consensus_network_events) =
-            network_provider.add_consensus(vec![
+        // Full nodes follow the ledger through state sync alone
+        let consensus_network = match self.role {
+            RoleType::Validator => Some(network_provider.add_consensus(vec![
                 ProtocolId::from_static(CONSENSUS_RPC_PROTOCOL),
                 ProtocolId::from_static(CONSENSUS_DIRECT_SEND_PROTOCOL),
-            ]);
+            ])),
+            RoleType::FullNode => None,
+        };
+
+        // Lets this node fetch ledger state it missed (e.g. while offline)
+        // from its peers, and serve the same to them
+        let (state_sync_sender, state_sync_events) =
+            network_provider.add_state_synchronizer(vec![ProtocolId::from_static(
+                STATE_SYNCHRONIZER_MSG_PROTOCOL,
+            )]);
 
         runtime.executor().spawn(network_provider.start());
         debug!("network started for peer_id: {}", &self.peer_id);
 
-        let executor = self.start_executor();
-        let consensus = self.start_consensus_provider(
+        let executor = self.start_executor()?;
+        let state_synchronizer = self.start_state_synchronizer(
             Arc::clone(&executor),
-            consensus_network_sender,
-            consensus_network_events,
-        )?;
+            state_sync_sender,
+            state_sync_events,
+        );
+
+        let consensus = match consensus_network {
+            Some((consensus_network_sender, consensus_network_events)) => {
+                Some(self.start_consensus_provider(
+                    Arc::clone(&executor),
+                    &state_synchronizer,
+                    consensus_network_sender,
+                    consensus_network_events,
+                )?)
+            }
+            None => None,
+        };
 
         Ok(Node {
             runtime,
             consensus,
+            state_synchronizer,
             mempool,
             executor,
+            storage,
         })
     }
 
     /// Start the network provider
-    fn start_network_provider(&mut self, runtime: &Runtime) -> Box<dyn LibraNetworkProvider> {
-        // NOTE: this is asserted to exist in `Launcher::new`
-        let network_signing_private = self.node_config.networks[0].network_keypairs.take_network_signing_private().expect(
-            "failed to move network signing private key out of NodeConfig: key not set or moved already"
-        );
+    fn start_network_provider(
+        &mut self,
+        runtime: &Runtime,
+    ) -> Result<Box<dyn LibraNetworkProvider>, LaunchError> {
+        // NOTE: this is checked to exist in `Launcher::new`
+        let network_signing_private = self.node_config.networks[0]
+            .network_keypairs
+            .take_network_signing_private()
+            .ok_or_else(|| LaunchError::MissingKey("network signing private key"))?;
 
         let network_signing_public = Ed25519PublicKey::from(&network_signing_private);
 
-        // NOTE: this is asserted to exist in `Launcher::new`
+        // NOTE: this is checked to exist in `Launcher::new`
         let network_config = &self.node_config.networks[0];
 
         let mut network_builder = NetworkBuilder::new(
@@ -160,23 +191,30 @@ where
             .peers
             .iter()
             .map(|(peer_id, keys)| {
-                (
-                    PeerId::from_str(peer_id).unwrap(),
-                    NetworkPublicKeys {
-                        signing_public_key: keys.network_signing_pubkey.clone(),
-                        identity_public_key: keys.network_identity_pubkey.clone(),
-                    },
-                )
+                let peer_id = PeerId::from_str(peer_id)
+                    .map_err(|e| LaunchError::invalid_peer_id(peer_id, e))?;
+
+                let keys = NetworkPublicKeys {
+                    signing_public_key: keys.network_signing_pubkey.clone(),
+                    identity_public_key: keys.network_identity_pubkey.clone(),
+                };
+
+                Ok((peer_id, keys))
             })
-            .collect();
+            .collect::<Result<_, LaunchError>>()?;
 
         let seed_peers = network_config
             .seed_peers
             .seed_peers
             .clone()
             .into_iter()
-            .map(|(peer_id, addrs)| (peer_id.try_into().expect("invalid PeerId"), addrs))
-            .collect();
+            .map(|(peer_id, addrs)| {
+                let peer_id = PeerId::try_from(peer_id.clone())
+                    .map_err(|e| LaunchError::invalid_peer_id(&peer_id, e))?;
+
+                Ok((peer_id, addrs))
+            })
+            .collect::<Result<_, LaunchError>>()?;
 
         network_builder
             .transport(TransportType::TcpNoise(Some(
@@ -193,11 +231,14 @@ where
         let (listen_addr, network_provider) = network_builder.build();
         debug!("listen addr: {:?}", listen_addr);
 
-        network_provider
+        Ok(network_provider)
     }
 
     /// Start the mempool for this node
-    fn start_mempool(&self, network_provider: &mut dyn LibraNetworkProvider) -> MempoolRuntime {
+    fn start_mempool(
+        &self,
+        network_provider: &mut dyn LibraNetworkProvider,
+    ) -> Result<MempoolRuntime, LaunchError> {
         let (network_sender, network_events) = network_provider
             .add_mempool(vec![ProtocolId::from_static(MEMPOOL_DIRECT_SEND_PROTOCOL)]);
 
@@ -228,12 +269,18 @@ where
                 config.mempool.mempool_service_port,
             )
             .build()
-            .expect("[mempool] unable to create grpc server");
+            .map_err(|e| LaunchError::Bind {
+                addr: format!(
+                    "{}:{}",
+                    config.mempool.address, config.mempool.mempool_service_port
+                ),
+                reason: e.to_string(),
+            })?;
 
         // setup shared mempool
         let storage_client: Arc<dyn StorageRead> = Arc::new(StorageReadServiceClient::new(
             Arc::new(EnvBuilder::new().name_prefix("grpc-mem-sto-").build()),
-            "localhost",
+            &config.storage.address,
             config.storage.port,
         ));
 
@@ -261,34 +308,41 @@ where
 
         debug!("mempool started in {} ms", instant.elapsed().as_millis());
 
-        MempoolRuntime {
+        Ok(MempoolRuntime {
             grpc_server: ServerHandle::setup(grpc_server),
             shared_mempool,
-        }
+        })
+    }
+
+    /// Start the state synchronizer, which syncs the ledger with peers
+    fn start_state_synchronizer<E>(
+        &self,
+        executor: Arc<Executor<E>>,
+        state_sync_sender: StateSynchronizerSender,
+        state_sync_events: StateSynchronizerEvents,
+    ) -> StateSynchronizer
+    where
+        E: VMExecutor + Send + Sync + 'static,
+    {
+        let state_sync_network_handles = vec![(self.peer_id, state_sync_sender, state_sync_events)];
+
+        StateSynchronizer::bootstrap(state_sync_network_handles, executor, &self.node_config)
     }
 
     /// Start the consensus provider
     fn start_consensus_provider<E>(
         &mut self,
         executor: Arc<Executor<E>>,
+        state_synchronizer: &StateSynchronizer,
         consensus_network_sender: ConsensusNetworkSender,
         consensus_network_events: ConsensusNetworkEvents,
-    ) -> Result<Box<dyn ConsensusProvider>, Error>
+    ) -> Result<Box<dyn ConsensusProvider>, LaunchError>
     where
         E: VMExecutor + Send + Sync + 'static,
     {
         // Initialize and start consensus.
         let instant = Instant::now();
 
-        // TODO(tarcieri): populate these?
-        let state_sync_network_handles = vec![];
-
-        let state_synchronizer = StateSynchronizer::bootstrap(
-            state_sync_network_handles,
-            Arc::clone(&executor),
-            &self.node_config,
-        );
-
         let mut consensus_provider = make_consensus_provider(
             &mut self.node_config,
             consensus_network_sender,
@@ -297,14 +351,17 @@ where
             state_synchronizer.create_client(),
         );
 
-        consensus_provider.start()?;
+        consensus_provider
+            .start()
+            .map_err(|e| LaunchError::Consensus(e.to_string()))?;
+
         debug!("consensus started in {} ms", instant.elapsed().as_millis());
 
         Ok(consensus_provider)
     }
 
-    /// Start the executor for this node
-    fn start_executor<E>(&self) -> Arc<Executor<E>>
+    /// Start the executor for this node, once storage is reachable
+    fn start_executor<E>(&self) -> Result<Arc<Executor<E>>, LaunchError>
     where
         E: VMExecutor + Send + Sync + 'static,
     {
@@ -323,10 +380,22 @@ where
             self.node_config.storage.grpc_max_receive_len,
         ));
 
-        Arc::new(Executor::new(
+        // The executor panics if it can't read its startup info, so make
+        // sure the storage service is answering first
+        storage_read_client
+            .get_startup_info()
+            .map_err(|e| LaunchError::Storage {
+                addr: format!(
+                    "{}:{}",
+                    self.node_config.storage.address, self.node_config.storage.port
+                ),
+                reason: e.to_string(),
+            })?;
+
+        Ok(Arc::new(Executor::new(
             Arc::clone(&storage_read_client) as Arc<dyn StorageRead>,
             storage_write_client,
             &self.node_config,
-        ))
+        )))
     }
 }
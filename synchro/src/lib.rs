@@ -5,7 +5,10 @@ pub mod config;
 pub mod crypto;
 pub mod error;
 pub mod launcher;
+pub mod log;
 pub mod node;
+pub mod proof;
+pub mod storage;
 pub mod transaction;
 
 // Crate re-exports
@@ -19,6 +22,7 @@ pub use libra_mempool as mempool;
 pub use libra_state_view as state_view;
 pub use libra_types as types;
 pub use network;
+pub use scratchpad;
 pub use state_synchronizer;
 pub use storage_client;
 pub use vm_runtime;
@@ -27,12 +31,11 @@ pub use vm_validator;
 // Other re-exports
 pub use tokio;
 
-pub use self::{launcher::Launcher, node::Node};
+pub use self::{launcher::Launcher, node::Node, storage::StorageRuntime};
 
 /// Helper to initialize a Tokio runtime
-pub fn start_runtime() -> tokio::runtime::Runtime {
+pub fn start_runtime() -> Result<tokio::runtime::Runtime, std::io::Error> {
     tokio::runtime::Builder::new()
         .name_prefix("synchro-")
         .build()
-        .unwrap_or_else(|e| panic!("couldn't initialize Tokio runtime: {}", e))
 }
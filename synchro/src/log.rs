@@ -0,0 +1,84 @@
+//! Append-only Merkle log.
+//!
+//! Trees are hashed as described in RFC 6962 (Certificate Transparency)
+//! using SHA-256, i.e. leaves are hashed as `SHA-256(0x00 || data)` and
+//! interior nodes as `SHA-256(0x01 || left || right)`.
+
+pub mod accumulator;
+pub mod ledger;
+pub mod proof;
+
+pub use self::{
+    accumulator::{Accumulator, MerkleLog},
+    ledger::LedgerProof,
+    proof::{ConsistencyProof, InclusionProof, ProofError},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Hashes of leaves and interior nodes of the log
+pub type Hash = [u8; 32];
+
+/// Size and root hash of the log at a particular point in time
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Number of leaves in the log
+    pub size: u64,
+
+    /// Root hash of the log
+    pub root: Hash,
+}
+
+/// Prefix for hashes of leaves
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix for hashes of interior nodes
+const NODE_PREFIX: u8 = 0x01;
+
+/// Compute the hash of a leaf containing the given data
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.input([LEAF_PREFIX]);
+    hasher.input(data);
+    to_hash(&hasher.result())
+}
+
+/// Compute the hash of an interior node from its children
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.input([NODE_PREFIX]);
+    hasher.input(left);
+    hasher.input(right);
+    to_hash(&hasher.result())
+}
+
+/// Hash of the empty tree (i.e. `SHA-256("")`)
+pub fn empty_root() -> Hash {
+    to_hash(&Sha256::digest(&[]))
+}
+
+/// Compute the root of the tree with the given leaf hashes
+pub(crate) fn subtree_root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => empty_root(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n as u64) as usize;
+            node_hash(&subtree_root(&leaves[..k]), &subtree_root(&leaves[k..]))
+        }
+    }
+}
+
+/// Largest power of two smaller than `n` (which must be at least 2)
+pub(crate) fn split_point(n: u64) -> u64 {
+    debug_assert!(n >= 2);
+    1 << (63 - (n - 1).leading_zeros())
+}
+
+/// Convert SHA-256 output to a `Hash`
+fn to_hash(output: &[u8]) -> Hash {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(output);
+    hash
+}
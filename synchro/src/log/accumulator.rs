@@ -0,0 +1,211 @@
+//! Merkle accumulators
+
+use super::{
+    empty_root, node_hash,
+    proof::{ConsistencyProof, InclusionProof},
+    split_point, subtree_root, Checkpoint, Hash,
+};
+use serde::{Deserialize, Serialize};
+
+/// Compact Merkle accumulator which stores only the roots of the log's
+/// perfect (i.e. frozen) subtrees.
+///
+/// This is enough to append to the log and compute its root, and is what
+/// gets kept in ledger state. Proofs are generated with [`MerkleLog`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Accumulator {
+    /// Number of leaves in the log
+    size: u64,
+
+    /// Roots of the frozen subtrees, largest (leftmost) first
+    frozen_subtree_roots: Vec<Hash>,
+}
+
+impl Accumulator {
+    /// Create a new, empty accumulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of leaves in the log
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Append a leaf hash, returning its index
+    pub fn append(&mut self, leaf_hash: Hash) -> u64 {
+        let index = self.size;
+        let mut hash = leaf_hash;
+
+        // Each trailing one bit of the old size is a subtree of equal height
+        // to merge with the new leaf
+        let mut size = self.size;
+        while size & 1 == 1 {
+            let left = self
+                .frozen_subtree_roots
+                .pop()
+                .expect("accumulator frozen subtrees inconsistent with size");
+
+            hash = node_hash(&left, &hash);
+            size >>= 1;
+        }
+
+        self.frozen_subtree_roots.push(hash);
+        self.size += 1;
+        index
+    }
+
+    /// Compute the root hash of the log
+    pub fn root(&self) -> Hash {
+        match self.frozen_subtree_roots.split_last() {
+            Some((last, rest)) => rest
+                .iter()
+                .rev()
+                .fold(*last, |right, left| node_hash(left, &right)),
+            None => empty_root(),
+        }
+    }
+
+    /// Get a checkpoint of the log's current size and root
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            size: self.size,
+            root: self.root(),
+        }
+    }
+}
+
+/// Merkle log with all of its leaf hashes, which can generate proofs
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MerkleLog {
+    /// Hashes of all leaves in the log
+    leaves: Vec<Hash>,
+}
+
+impl MerkleLog {
+    /// Create a new, empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a log from its leaf hashes
+    pub fn from_leaf_hashes(leaves: Vec<Hash>) -> Self {
+        Self { leaves }
+    }
+
+    /// Number of leaves in the log
+    pub fn size(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    /// Append a leaf hash, returning its index
+    pub fn append(&mut self, leaf_hash: Hash) -> u64 {
+        self.leaves.push(leaf_hash);
+        self.size() - 1
+    }
+
+    /// Compute the root hash of the log
+    pub fn root(&self) -> Hash {
+        subtree_root(&self.leaves)
+    }
+
+    /// Compute the root hash of the log as of the given size
+    pub fn root_at(&self, size: u64) -> Option<Hash> {
+        if size > self.size() {
+            return None;
+        }
+
+        Some(subtree_root(&self.leaves[..size as usize]))
+    }
+
+    /// Get a checkpoint of the log as of the given size
+    pub fn checkpoint_at(&self, size: u64) -> Option<Checkpoint> {
+        self.root_at(size).map(|root| Checkpoint { size, root })
+    }
+
+    /// Generate a proof that the leaf at the given index is included in the
+    /// log as of the given size
+    pub fn inclusion_proof(&self, leaf_index: u64, tree_size: u64) -> Option<InclusionProof> {
+        if leaf_index >= tree_size || tree_size > self.size() {
+            return None;
+        }
+
+        let mut audit_path = vec![];
+        inclusion_path(
+            leaf_index as usize,
+            &self.leaves[..tree_size as usize],
+            &mut audit_path,
+        );
+
+        Some(InclusionProof {
+            leaf_index,
+            tree_size,
+            audit_path,
+        })
+    }
+
+    /// Generate a proof that the log as of `new_size` is an append-only
+    /// extension of the log as of `old_size`
+    pub fn consistency_proof(&self, old_size: u64, new_size: u64) -> Option<ConsistencyProof> {
+        if old_size > new_size || new_size > self.size() {
+            return None;
+        }
+
+        let mut path = vec![];
+
+        if old_size > 0 && old_size < new_size {
+            consistency_path(
+                old_size as usize,
+                &self.leaves[..new_size as usize],
+                true,
+                &mut path,
+            );
+        }
+
+        Some(ConsistencyProof {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+}
+
+/// Compute the audit path for a leaf (RFC 6962 Section 2.1.1)
+fn inclusion_path(index: usize, leaves: &[Hash], path: &mut Vec<Hash>) {
+    if leaves.len() <= 1 {
+        return;
+    }
+
+    let k = split_point(leaves.len() as u64) as usize;
+
+    if index < k {
+        inclusion_path(index, &leaves[..k], path);
+        path.push(subtree_root(&leaves[k..]));
+    } else {
+        inclusion_path(index - k, &leaves[k..], path);
+        path.push(subtree_root(&leaves[..k]));
+    }
+}
+
+/// Compute the consistency proof path for the first `m` leaves
+/// (RFC 6962 Section 2.1.2)
+fn consistency_path(m: usize, leaves: &[Hash], complete: bool, path: &mut Vec<Hash>) {
+    let n = leaves.len();
+
+    if m == n {
+        if !complete {
+            path.push(subtree_root(leaves));
+        }
+        return;
+    }
+
+    let k = split_point(n as u64) as usize;
+
+    if m <= k {
+        consistency_path(m, &leaves[..k], complete, path);
+        path.push(subtree_root(&leaves[k..]));
+    } else {
+        consistency_path(m - k, &leaves[k..], false, path);
+        path.push(subtree_root(&leaves[..k]));
+    }
+}
@@ -0,0 +1,110 @@
+//! Proofs binding the log to ledger state signed by a quorum of validators
+
+use super::{
+    accumulator::Accumulator,
+    leaf_hash,
+    proof::{InclusionProof, ProofError},
+};
+use libra_types::{
+    account_address::AccountAddress, account_state_blob::AccountStateWithProof,
+    ledger_info::LedgerInfoWithSignatures, validator_verifier::ValidatorVerifier,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::TryFrom};
+
+/// Proof that a leaf is included in a log whose accumulator is stored in
+/// the state of a particular account, as of a ledger info signed by a quorum
+/// of validators.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LedgerProof {
+    /// Proof the leaf is included in the log
+    pub inclusion: InclusionProof,
+
+    /// State of the account holding the log's accumulator, along with a
+    /// proof of its inclusion in the ledger. The account should hold nothing
+    /// but the accumulator, since its whole state is carried in the proof.
+    pub account_state: AccountStateWithProof,
+
+    /// Ledger info signed by a quorum of validators
+    pub ledger_info: LedgerInfoWithSignatures,
+}
+
+impl LedgerProof {
+    /// Verify the given leaf is included in the log stored at `log_path` in
+    /// the state of the given account, and that the ledger info is signed
+    /// by a quorum of the validator set.
+    pub fn verify(
+        &self,
+        validator_verifier: &ValidatorVerifier,
+        address: AccountAddress,
+        log_path: &[u8],
+        leaf: &[u8],
+    ) -> Result<(), ProofError> {
+        let accumulator = verify_accumulator(
+            &self.ledger_info,
+            &self.account_state,
+            validator_verifier,
+            address,
+            log_path,
+        )?;
+
+        if accumulator.size() != self.inclusion.tree_size {
+            return Err(ProofError::SizeMismatch {
+                proof_size: self.inclusion.tree_size,
+                log_size: accumulator.size(),
+            });
+        }
+
+        self.inclusion.verify(&leaf_hash(leaf), &accumulator.root())
+    }
+
+    /// Get the log's accumulator from the account state in this proof
+    /// (without verifying it)
+    pub fn accumulator(&self, log_path: &[u8]) -> Result<Accumulator, ProofError> {
+        account_accumulator(&self.account_state, log_path)
+    }
+}
+
+/// Verify the ledger info is signed by a quorum of the validator set and the
+/// given account's state is included in it, returning the log's accumulator
+/// (which is empty if the account holds no log yet) from the verified state
+pub fn verify_accumulator(
+    ledger_info: &LedgerInfoWithSignatures,
+    account_state: &AccountStateWithProof,
+    validator_verifier: &ValidatorVerifier,
+    address: AccountAddress,
+    log_path: &[u8],
+) -> Result<Accumulator, ProofError> {
+    ledger_info
+        .verify(validator_verifier)
+        .map_err(|e| ProofError::Signatures(e.to_string()))?;
+
+    account_state
+        .verify(ledger_info.ledger_info(), account_state.version, address)
+        .map_err(|e| ProofError::State(e.to_string()))?;
+
+    match account_state.blob {
+        Some(_) => account_accumulator(account_state, log_path),
+        None => Ok(Accumulator::new()),
+    }
+}
+
+/// Get the log's accumulator from an account state (without verifying it)
+fn account_accumulator(
+    account_state: &AccountStateWithProof,
+    log_path: &[u8],
+) -> Result<Accumulator, ProofError> {
+    let blob = account_state
+        .blob
+        .as_ref()
+        .ok_or_else(|| ProofError::State("account does not exist".to_owned()))?;
+
+    let account_state = BTreeMap::<Vec<u8>, Vec<u8>>::try_from(blob)
+        .map_err(|e| ProofError::State(e.to_string()))?;
+
+    let bytes = account_state
+        .get(log_path)
+        .ok_or_else(|| ProofError::State("log not found in account state".to_owned()))?;
+
+    bincode::deserialize(bytes).map_err(|e| ProofError::State(e.to_string()))
+}
@@ -0,0 +1,212 @@
+//! Merkle log proofs
+
+use super::{node_hash, Hash};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors which occur when verifying proofs
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum ProofError {
+    /// Leaf index is outside of the tree
+    #[error("leaf index {leaf_index} out of range for tree of size {tree_size}")]
+    IndexOutOfRange {
+        /// Index of the leaf
+        leaf_index: u64,
+
+        /// Size of the tree
+        tree_size: u64,
+    },
+
+    /// Proof has the wrong number of hashes for the tree it's claimed to be for
+    #[error("malformed proof: wrong number of hashes")]
+    Malformed,
+
+    /// Root hash computed from the proof doesn't match the expected root
+    #[error("root hash mismatch")]
+    RootMismatch,
+
+    /// Sizes given for a consistency proof are invalid
+    #[error("invalid log sizes for consistency proof: {old_size} to {new_size}")]
+    InvalidSizes {
+        /// Size of the older log
+        old_size: u64,
+
+        /// Size of the newer log
+        new_size: u64,
+    },
+
+    /// Proof is for a different log size than the one in the ledger state
+    #[error("proof is for log size {proof_size} but ledger contains {log_size} leaves")]
+    SizeMismatch {
+        /// Log size the proof was generated against
+        proof_size: u64,
+
+        /// Log size found in the ledger state
+        log_size: u64,
+    },
+
+    /// Ledger info isn't signed by a quorum of the validator set
+    #[error("invalid ledger info signatures: {0}")]
+    Signatures(String),
+
+    /// Account state doesn't verify against the ledger info, or doesn't
+    /// contain the log
+    #[error("invalid account state: {0}")]
+    State(String),
+}
+
+/// Proof that a leaf is included in a log of a particular size
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Index of the leaf in the log
+    pub leaf_index: u64,
+
+    /// Size of the log the proof was generated against
+    pub tree_size: u64,
+
+    /// Sibling hashes from the leaf up to the root
+    pub audit_path: Vec<Hash>,
+}
+
+impl InclusionProof {
+    /// Compute the root hash implied by this proof for the given leaf hash
+    pub fn root(&self, leaf_hash: &Hash) -> Result<Hash, ProofError> {
+        if self.leaf_index >= self.tree_size {
+            return Err(ProofError::IndexOutOfRange {
+                leaf_index: self.leaf_index,
+                tree_size: self.tree_size,
+            });
+        }
+
+        // RFC 9162 Section 2.1.3.2
+        let mut index = self.leaf_index;
+        let mut last = self.tree_size - 1;
+        let mut root = *leaf_hash;
+
+        for sibling in &self.audit_path {
+            if last == 0 {
+                return Err(ProofError::Malformed);
+            }
+
+            if index & 1 == 1 || index == last {
+                root = node_hash(sibling, &root);
+
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                root = node_hash(&root, sibling);
+            }
+
+            index >>= 1;
+            last >>= 1;
+        }
+
+        if last != 0 {
+            return Err(ProofError::Malformed);
+        }
+
+        Ok(root)
+    }
+
+    /// Verify that the given leaf hash is included in a log with the given root
+    pub fn verify(&self, leaf_hash: &Hash, expected_root: &Hash) -> Result<(), ProofError> {
+        if self.root(leaf_hash)? == *expected_root {
+            Ok(())
+        } else {
+            Err(ProofError::RootMismatch)
+        }
+    }
+}
+
+/// Proof that a log of `new_size` leaves is an append-only extension of the
+/// same log when it contained `old_size` leaves
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    /// Size of the older log
+    pub old_size: u64,
+
+    /// Size of the newer log
+    pub new_size: u64,
+
+    /// Subtree hashes needed to compute both roots
+    pub path: Vec<Hash>,
+}
+
+impl ConsistencyProof {
+    /// Verify the log with root `new_root` is an extension of the log with
+    /// root `old_root`
+    pub fn verify(&self, old_root: &Hash, new_root: &Hash) -> Result<(), ProofError> {
+        let (old_size, new_size) = (self.old_size, self.new_size);
+
+        if old_size > new_size {
+            return Err(ProofError::InvalidSizes { old_size, new_size });
+        }
+
+        // Every log is consistent with itself and with the empty log
+        if old_size == new_size || old_size == 0 {
+            if !self.path.is_empty() {
+                return Err(ProofError::Malformed);
+            }
+
+            if old_size != 0 && old_root != new_root {
+                return Err(ProofError::RootMismatch);
+            }
+
+            return Ok(());
+        }
+
+        // RFC 9162 Section 2.1.4.2
+        let mut path = self.path.iter();
+
+        // If the old log is a perfect subtree, its root is the first hash
+        let seed = if old_size.is_power_of_two() {
+            old_root
+        } else {
+            path.next().ok_or(ProofError::Malformed)?
+        };
+
+        let mut index = old_size - 1;
+        let mut last = new_size - 1;
+
+        while index & 1 == 1 {
+            index >>= 1;
+            last >>= 1;
+        }
+
+        let mut old_hash = *seed;
+        let mut new_hash = *seed;
+
+        for sibling in path {
+            if last == 0 {
+                return Err(ProofError::Malformed);
+            }
+
+            if index & 1 == 1 || index == last {
+                old_hash = node_hash(sibling, &old_hash);
+                new_hash = node_hash(sibling, &new_hash);
+
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                new_hash = node_hash(&new_hash, sibling);
+            }
+
+            index >>= 1;
+            last >>= 1;
+        }
+
+        if last != 0 {
+            return Err(ProofError::Malformed);
+        }
+
+        if old_hash == *old_root && new_hash == *new_root {
+            Ok(())
+        } else {
+            Err(ProofError::RootMismatch)
+        }
+    }
+}
@@ -1,12 +1,22 @@
 //! Synchronicity node type: owns all state for a running node
 
+use crate::storage::StorageRuntime;
 use consensus::consensus_provider::ConsensusProvider;
 use executor::Executor;
 use libra_mempool::MempoolRuntime;
+use log::{debug, error, info};
+use signal_hook::{iterator::Signals, SIGINT, SIGTERM};
+use state_synchronizer::StateSynchronizer;
 use std::sync::Arc;
 use vm_runtime::VMExecutor;
 
-/// Synchronicity full node runtime
+/// Exit status after a clean shutdown
+pub const EXIT_SUCCESS: i32 = 0;
+
+/// Exit status when the node couldn't wait for a shutdown signal
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Synchronicity node runtime (either a validator or a full node)
 pub struct Node<V>
 where
     V: VMExecutor + Send + Sync + 'static,
@@ -14,12 +24,76 @@ where
     /// Tokio runtime
     pub runtime: tokio::runtime::Runtime,
 
-    /// Consensus provider
-    pub consensus: Box<dyn ConsensusProvider>,
+    /// Consensus provider (validators only)
+    pub consensus: Option<Box<dyn ConsensusProvider>>,
+
+    /// State synchronizer
+    pub state_synchronizer: StateSynchronizer,
 
     /// Mempool runtime
     pub mempool: MempoolRuntime,
 
     /// Executor
     pub executor: Arc<Executor<V>>,
+
+    /// Embedded storage service.
+    ///
+    /// Declared last so it's dropped after everything which uses it.
+    pub storage: StorageRuntime,
+}
+
+impl<V> Node<V>
+where
+    V: VMExecutor + Send + Sync + 'static,
+{
+    /// Block until the process receives SIGINT or SIGTERM, then shut the
+    /// node down and return the status the process should exit with
+    pub fn run_until_shutdown(self) -> i32 {
+        let status = match Signals::new(&[SIGINT, SIGTERM]) {
+            Ok(signals) => {
+                if let Some(signal) = signals.forever().next() {
+                    info!("received signal {}; shutting down", signal);
+                }
+
+                EXIT_SUCCESS
+            }
+            Err(e) => {
+                error!("couldn't install signal handlers: {}; shutting down", e);
+                EXIT_FAILURE
+            }
+        };
+
+        self.shutdown();
+        status
+    }
+
+    /// Shut the node down, in the reverse order it was launched
+    pub fn shutdown(self) {
+        let Node {
+            runtime,
+            mut consensus,
+            state_synchronizer,
+            mempool,
+            executor,
+            storage,
+        } = self;
+
+        if let Some(consensus) = &mut consensus {
+            consensus.stop();
+            debug!("consensus stopped");
+        }
+
+        // Dropping the gRPC server handle waits for in-flight requests
+        drop(mempool);
+        debug!("mempool stopped");
+
+        drop(consensus);
+        drop(state_synchronizer);
+        drop(executor);
+        drop(runtime);
+
+        // Waits for LibraDB to be flushed and closed
+        drop(storage);
+        debug!("storage closed");
+    }
 }
@@ -0,0 +1,207 @@
+//! Static proofs-of-reproducibility.
+//!
+//! Proof files are self-contained: they bundle a leaf of the log, a proof of
+//! its inclusion in ledger state signed by a quorum of validators, and the
+//! validator set the ledger info was signed under, so they can be obtained
+//! once, shipped alongside a build, and verified completely offline.
+//!
+//! ## Encoding
+//!
+//! - 8-byte magic string: `SYNPROOF`
+//! - 2-byte big endian format version (currently `1`)
+//! - [`ProofFile`] serialized using `bincode`'s default configuration
+//!
+//! Parsing is strict: trailing data and non-canonical encodings are rejected.
+
+use crate::{
+    config::PeerInfo,
+    log::{LedgerProof, ProofError},
+};
+use libra_config::trusted_peers::ConsensusPeersConfig;
+use libra_crypto::ed25519::Ed25519PublicKey;
+use libra_types::{account_address::AccountAddress, validator_verifier::ValidatorVerifier};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path, str::FromStr};
+use thiserror::Error;
+
+/// Magic string at the beginning of every proof file
+pub const MAGIC: &[u8; 8] = b"SYNPROOF";
+
+/// Current version of the proof file format
+pub const VERSION: u16 = 1;
+
+/// File extension for proof files
+pub const FILE_EXTENSION: &str = "synproof";
+
+/// Errors which occur when parsing proof files
+#[derive(Debug, Error)]
+pub enum FormatError {
+    /// File doesn't begin with the expected magic string
+    #[error("not a proof file (bad magic)")]
+    BadMagic,
+
+    /// File is a version of the format we don't support
+    #[error("unsupported proof file version: {0}")]
+    UnsupportedVersion(u16),
+
+    /// File contents are malformed
+    #[error("malformed proof file: {0}")]
+    Malformed(String),
+
+    /// File contents aren't canonically encoded (e.g. trailing data)
+    #[error("proof file is not canonically encoded")]
+    NonCanonical,
+
+    /// Validator set doesn't contain any validators
+    #[error("no validators in validator set")]
+    NoValidators,
+
+    /// I/O error reading or writing a proof file
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Set of validators and their consensus public keys
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorSet {
+    /// Consensus public keys for each validator
+    pub validators: BTreeMap<AccountAddress, Ed25519PublicKey>,
+}
+
+impl ValidatorSet {
+    /// Create a validator set from a consensus peers config
+    pub fn from_consensus_peers(config: &ConsensusPeersConfig) -> Result<Self, FormatError> {
+        let mut validators = BTreeMap::new();
+
+        for (peer_id, peer_info) in &config.peers {
+            let account = parse_peer_id(peer_id)?;
+
+            validators.insert(account, peer_info.consensus_pubkey.clone());
+        }
+
+        Ok(Self { validators })
+    }
+
+    /// Create a validator set from the `PeerInfo` of each peer, skipping full
+    /// nodes (which never sign the ledger). At least one validator is required.
+    pub fn from_peer_infos(peers: &[PeerInfo]) -> Result<Self, FormatError> {
+        let mut validators = BTreeMap::new();
+
+        for peer in peers.iter().filter(|peer| peer.is_validator()) {
+            let account = parse_peer_id(&peer.id)?;
+
+            validators.insert(account, peer.consensus.consensus_pubkey.clone());
+        }
+
+        if validators.is_empty() {
+            return Err(FormatError::NoValidators);
+        }
+
+        Ok(Self { validators })
+    }
+
+    /// Get a `ValidatorVerifier` which requires a 2f+1 quorum of this set
+    pub fn verifier(&self) -> ValidatorVerifier {
+        ValidatorVerifier::new(self.validators.clone().into_iter().collect())
+    }
+}
+
+/// Self-contained proof that a leaf is included in the log
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProofFile {
+    /// Account whose state holds the log
+    pub address: AccountAddress,
+
+    /// Path within the account's state where the log's accumulator is kept
+    pub log_path: Vec<u8>,
+
+    /// Leaf being proven
+    pub leaf: Vec<u8>,
+
+    /// Proof the leaf is included in quorum-signed ledger state
+    pub proof: LedgerProof,
+
+    /// Validator set the ledger info was signed under
+    pub validator_set: ValidatorSet,
+}
+
+impl ProofFile {
+    /// Parse a proof file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        if bytes.len() < MAGIC.len() + 2 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+
+        let version = u16::from_be_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let body = &bytes[MAGIC.len() + 2..];
+        let proof_file: Self =
+            bincode::deserialize(body).map_err(|e| FormatError::Malformed(e.to_string()))?;
+
+        // Re-encoding must reproduce the original body exactly
+        if proof_file.body_bytes() != body {
+            return Err(FormatError::NonCanonical);
+        }
+
+        Ok(proof_file)
+    }
+
+    /// Serialize this proof file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_be_bytes());
+        bytes.extend_from_slice(&self.body_bytes());
+        bytes
+    }
+
+    /// Load a proof file from disk
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FormatError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Save this proof file to disk
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FormatError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Verify this proof against the validator set it bundles
+    pub fn verify(&self) -> Result<(), ProofError> {
+        self.verify_with(&self.validator_set)
+    }
+
+    /// Verify this proof against the given (trusted) validator set
+    pub fn verify_with(&self, validator_set: &ValidatorSet) -> Result<(), ProofError> {
+        // An empty quorum would be satisfied by no signatures at all
+        if validator_set.validators.is_empty() {
+            return Err(ProofError::Signatures("empty validator set".to_owned()));
+        }
+
+        self.proof.verify(
+            &validator_set.verifier(),
+            self.address,
+            &self.log_path,
+            &self.leaf,
+        )
+    }
+
+    /// Serialize the body of this proof file
+    fn body_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("proof file serialization failed")
+    }
+}
+
+/// Parse a validator's peer ID into its account address
+fn parse_peer_id(peer_id: &str) -> Result<AccountAddress, FormatError> {
+    // An empty string would otherwise parse as the all-zeroes address
+    if peer_id.is_empty() {
+        return Err(FormatError::Malformed("empty peer ID".to_owned()));
+    }
+
+    AccountAddress::from_str(peer_id)
+        .map_err(|e| FormatError::Malformed(format!("invalid peer ID: {}", e)))
+}
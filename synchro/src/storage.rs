@@ -0,0 +1,83 @@
+//! Embedded storage service: serves a LibraDB from within the node process
+
+use crate::error::LaunchError;
+use grpc_helpers::ServerHandle;
+use grpcio::EnvBuilder;
+use libra_config::config::NodeConfig;
+use log::{debug, warn};
+use std::{
+    fs,
+    sync::{mpsc, Arc},
+    time::{Duration, Instant},
+};
+use storage_proto::proto::storage::create_storage;
+use storage_service::StorageService;
+
+/// How long to wait for LibraDB to be closed when shutting down
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Storage runtime: LibraDB and the storage gRPC service in front of it
+pub struct StorageRuntime {
+    /// gRPC server (taken when dropped)
+    grpc_server: Option<ServerHandle>,
+
+    /// Notified once LibraDB has been closed
+    shutdown_receiver: mpsc::Receiver<()>,
+}
+
+impl StorageRuntime {
+    /// Open LibraDB in the configured storage directory and serve it on the
+    /// configured storage address and port
+    pub fn start(config: &NodeConfig) -> Result<Self, LaunchError> {
+        let instant = Instant::now();
+        let addr = format!("{}:{}", config.storage.address, config.storage.port);
+        let storage_dir = config.get_storage_dir();
+
+        fs::create_dir_all(&storage_dir).map_err(|e| LaunchError::Storage {
+            addr: addr.clone(),
+            reason: format!("couldn't create {}: {}", storage_dir.display(), e),
+        })?;
+
+        let (storage_service, shutdown_receiver) = StorageService::new(&storage_dir);
+
+        let env = Arc::new(EnvBuilder::new().name_prefix("grpc-storage-").build());
+        let grpc_server = grpcio::ServerBuilder::new(env)
+            .register_service(create_storage(storage_service))
+            .bind(config.storage.address.clone(), config.storage.port)
+            .build()
+            .map_err(|e| LaunchError::Bind {
+                addr,
+                reason: e.to_string(),
+            })?;
+
+        debug!(
+            "storage started in {} ms (dir: {})",
+            instant.elapsed().as_millis(),
+            storage_dir.display()
+        );
+
+        Ok(Self {
+            grpc_server: Some(ServerHandle::setup(grpc_server)),
+            shutdown_receiver,
+        })
+    }
+}
+
+impl Drop for StorageRuntime {
+    /// Shut down the gRPC server, then wait (up to `SHUTDOWN_TIMEOUT`) for
+    /// LibraDB to be closed
+    fn drop(&mut self) {
+        self.grpc_server.take();
+
+        match self.shutdown_receiver.recv_timeout(SHUTDOWN_TIMEOUT) {
+            Ok(()) => (),
+            Err(mpsc::RecvTimeoutError::Timeout) => warn!(
+                "storage didn't shut down within {} s; not waiting for it",
+                SHUTDOWN_TIMEOUT.as_secs()
+            ),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                debug!("storage shutdown notification lost")
+            }
+        }
+    }
+}
@@ -1,12 +1,13 @@
 //! Transaction-related types/traits
 
 pub use libra_types::{
-    transaction::{SignedTransaction, Transaction, TransactionOutput},
+    transaction::{SignedTransaction, Transaction, TransactionOutput, TransactionPayload},
     vm_error::VMStatus as Status,
 };
 pub use vm_runtime::VMVerifier;
 pub use vm_validator::vm_validator::TransactionValidation;
 
+use libra_types::transaction::Script;
 use std::sync::Arc;
 use storage_client::StorageRead;
 
@@ -18,3 +19,53 @@ pub trait NewVerifier: Send + Sync {
     /// Initialize a transaction validator from the given storage reader
     fn new_verifier(&self, storage_read_client: Arc<dyn StorageRead>) -> Self::Verifier;
 }
+
+/// Application-defined payload carried in the `code` of a transaction's
+/// script, prefixed with a version byte.
+///
+/// The version allows the body encoding to evolve: nodes keep decoding the
+/// versions they already understand, and a new version is only submitted
+/// once every node in the group has been upgraded to support it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionedPayload {
+    /// Payload format version
+    pub version: u8,
+
+    /// Encoded payload body
+    pub body: Vec<u8>,
+}
+
+impl VersionedPayload {
+    /// Create a new versioned payload
+    pub fn new(version: u8, body: Vec<u8>) -> Self {
+        Self { version, body }
+    }
+
+    /// Extract the versioned payload from a signed transaction (if present)
+    pub fn from_transaction(txn: &SignedTransaction) -> Option<Self> {
+        match txn.payload() {
+            TransactionPayload::Script(script) => Self::from_bytes(script.code()),
+            _ => None,
+        }
+    }
+
+    /// Decode a versioned payload from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes
+            .split_first()
+            .map(|(version, body)| Self::new(*version, body.to_vec()))
+    }
+
+    /// Encode this versioned payload as bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.body.len());
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    /// Wrap this payload in a `TransactionPayload` for signing
+    pub fn to_transaction_payload(&self) -> TransactionPayload {
+        TransactionPayload::Script(Script::new(self.to_bytes(), vec![]))
+    }
+}
@@ -0,0 +1,69 @@
+//! Merkle log tests
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+use synchro::log::{self, Accumulator, MerkleLog};
+
+/// Number of leaves to test with
+const LOG_SIZE: u64 = 40;
+
+/// Build a log and an accumulator with `LOG_SIZE` leaves
+fn example_log() -> (MerkleLog, Accumulator) {
+    let mut merkle_log = MerkleLog::new();
+    let mut accumulator = Accumulator::new();
+    assert_eq!(merkle_log.root(), accumulator.root());
+
+    for i in 0..LOG_SIZE {
+        let leaf_hash = log::leaf_hash(&i.to_le_bytes());
+        assert_eq!(merkle_log.append(leaf_hash), i);
+        assert_eq!(accumulator.append(leaf_hash), i);
+        assert_eq!(merkle_log.root(), accumulator.root());
+    }
+
+    (merkle_log, accumulator)
+}
+
+#[test]
+fn inclusion_proofs() {
+    let (merkle_log, _) = example_log();
+
+    for tree_size in 1..=LOG_SIZE {
+        let root = merkle_log.root_at(tree_size).unwrap();
+
+        for leaf_index in 0..tree_size {
+            let proof = merkle_log.inclusion_proof(leaf_index, tree_size).unwrap();
+            let leaf_hash = log::leaf_hash(&leaf_index.to_le_bytes());
+            proof.verify(&leaf_hash, &root).unwrap();
+
+            let wrong_leaf_hash = log::leaf_hash(&tree_size.to_le_bytes());
+            assert!(proof.verify(&wrong_leaf_hash, &root).is_err());
+        }
+    }
+
+    assert!(merkle_log.inclusion_proof(LOG_SIZE, LOG_SIZE).is_none());
+}
+
+#[test]
+fn consistency_proofs() {
+    let (merkle_log, _) = example_log();
+
+    for new_size in 0..=LOG_SIZE {
+        let new_root = merkle_log.root_at(new_size).unwrap();
+
+        for old_size in 0..=new_size {
+            let old_root = merkle_log.root_at(old_size).unwrap();
+            let proof = merkle_log.consistency_proof(old_size, new_size).unwrap();
+            proof.verify(&old_root, &new_root).unwrap();
+
+            if old_size > 0 && old_size < new_size {
+                let wrong_root = merkle_log.root_at(old_size - 1).unwrap();
+                assert!(proof.verify(&wrong_root, &new_root).is_err());
+                assert!(proof.verify(&old_root, &wrong_root).is_err());
+            }
+        }
+    }
+
+    assert!(merkle_log.consistency_proof(2, 1).is_none());
+    assert!(merkle_log.consistency_proof(1, LOG_SIZE + 1).is_none());
+}
@@ -0,0 +1,136 @@
+//! Proof file encoding tests
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+use synchro::{
+    crypto::hash::HashValue,
+    log::{self, LedgerProof, MerkleLog, ProofError},
+    proof::{FormatError, ProofFile, ValidatorSet, MAGIC, VERSION},
+    types::{
+        account_address::AccountAddress,
+        account_state_blob::AccountStateWithProof,
+        ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+        proof::{AccountStateProof, AccumulatorProof, SparseMerkleProof},
+        transaction::TransactionInfo,
+        vm_error::StatusCode,
+    },
+};
+
+/// Leaf the example proof is for
+const LEAF: &[u8] = b"example leaf";
+
+#[test]
+fn round_trip() {
+    let proof_file = example_proof_file();
+    let bytes = proof_file.to_bytes();
+
+    assert_eq!(&bytes[..MAGIC.len()], MAGIC);
+    assert_eq!(ProofFile::from_bytes(&bytes).unwrap(), proof_file);
+}
+
+#[test]
+fn bad_magic_rejected() {
+    let mut bytes = example_proof_file().to_bytes();
+    bytes[0] ^= 0xFF;
+
+    match ProofFile::from_bytes(&bytes) {
+        Err(FormatError::BadMagic) => (),
+        other => panic!("expected bad magic, got {:?}", other),
+    }
+
+    // Too short to even hold the header
+    match ProofFile::from_bytes(&MAGIC[..]) {
+        Err(FormatError::BadMagic) => (),
+        other => panic!("expected bad magic, got {:?}", other),
+    }
+}
+
+#[test]
+fn unsupported_version_rejected() {
+    let mut bytes = example_proof_file().to_bytes();
+    bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(VERSION + 1).to_be_bytes());
+
+    match ProofFile::from_bytes(&bytes) {
+        Err(FormatError::UnsupportedVersion(version)) => assert_eq!(version, VERSION + 1),
+        other => panic!("expected unsupported version, got {:?}", other),
+    }
+}
+
+#[test]
+fn trailing_bytes_rejected() {
+    let mut bytes = example_proof_file().to_bytes();
+    bytes.push(0);
+
+    match ProofFile::from_bytes(&bytes) {
+        Err(FormatError::NonCanonical) => (),
+        other => panic!("expected non-canonical encoding, got {:?}", other),
+    }
+}
+
+#[test]
+fn truncated_body_rejected() {
+    let bytes = example_proof_file().to_bytes();
+
+    for len in &[MAGIC.len() + 2, bytes.len() / 2, bytes.len() - 1] {
+        match ProofFile::from_bytes(&bytes[..*len]) {
+            Err(FormatError::Malformed(_)) => (),
+            other => panic!("expected malformed file at {} bytes, got {:?}", len, other),
+        }
+    }
+}
+
+#[test]
+fn empty_validator_set_rejected() {
+    match ValidatorSet::from_peer_infos(&[]) {
+        Err(FormatError::NoValidators) => (),
+        other => panic!("expected no validators, got {:?}", other),
+    }
+
+    // No signatures at all would otherwise be a quorum
+    match example_proof_file().verify_with(&ValidatorSet::default()) {
+        Err(ProofError::Signatures(_)) => (),
+        other => panic!("expected invalid signatures, got {:?}", other),
+    }
+}
+
+/// Proof file for `LEAF` in a log containing only it. The ledger parts of the
+/// proof are placeholders: these tests only exercise the encoding.
+fn example_proof_file() -> ProofFile {
+    let mut merkle_log = MerkleLog::new();
+    merkle_log.append(log::leaf_hash(LEAF));
+
+    let ledger_info = LedgerInfo::new(
+        0,
+        HashValue::zero(),
+        HashValue::zero(),
+        HashValue::zero(),
+        0,
+        0,
+        None,
+    );
+
+    let account_state_proof = AccountStateProof::new(
+        AccumulatorProof::new(vec![]),
+        TransactionInfo::new(
+            HashValue::zero(),
+            HashValue::zero(),
+            HashValue::zero(),
+            0,
+            StatusCode::EXECUTED,
+        ),
+        SparseMerkleProof::new(None, vec![]),
+    );
+
+    ProofFile {
+        address: AccountAddress::default(),
+        log_path: b"example/log".to_vec(),
+        leaf: LEAF.to_vec(),
+        proof: LedgerProof {
+            inclusion: merkle_log.inclusion_proof(0, 1).unwrap(),
+            account_state: AccountStateWithProof::new(0, None, account_state_proof),
+            ledger_info: LedgerInfoWithSignatures::new(ledger_info, Default::default()),
+        },
+        validator_set: ValidatorSet::default(),
+    }
+}
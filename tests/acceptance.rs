@@ -5,9 +5,28 @@
 #![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
 
 use abscissa_core::testing::prelude::*;
-use std::{fs, path::Path};
-use synchro::config::{ConsensusConfig, NetworkConfig, NodeConfig, PeerInfo, PersistableConfig};
-use synchronicity::config::SynchronicityConfig;
+use std::{collections::BTreeMap, convert::TryFrom, fs, path::Path};
+use synchro::{
+    config::{
+        ConsensusConfig, NetworkConfig, NodeConfig, PeerInfo, PeerInfoSet, PersistableConfig,
+    },
+    crypto::{
+        ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+        hash::{CryptoHash, HashValue},
+        traits::SigningKey,
+    },
+    log::{self, LedgerProof, MerkleLog},
+    proof::{ProofFile, ValidatorSet},
+    types::{
+        account_address::AccountAddress,
+        account_state_blob::AccountStateWithProof,
+        ledger_info::{LedgerInfo, LedgerInfoWithSignatures},
+        proof::{AccountStateProof, AccumulatorProof, SparseMerkleProof},
+        transaction::TransactionInfo,
+        vm_error::StatusCode,
+    },
+};
+use synchronicity::{config::SynchronicityConfig, state};
 use tempfile::tempdir;
 
 #[test]
@@ -22,6 +41,7 @@ fn config_generator() {
     let config = SynchronicityConfig::load_config(dir.join("synchronicity.toml"));
     assert_eq!(&config.node_config, &dir.join("node.config.toml"));
     assert_eq!(&config.scratch_dir, &dir.join("scratch"));
+    assert_eq!(config.storage_dir.as_ref().unwrap(), &dir.join("db"));
 
     // Make sure the scratch directory exists
     assert!(fs::metadata(&config.scratch_dir).unwrap().is_dir());
@@ -43,6 +63,310 @@ fn config_generator() {
     PeerInfo::load_config(dir.join("peer_info.toml"));
 }
 
+#[test]
+fn init_from_mnemonic() {
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+    let (original_dir, restored_dir) = (dir.join("original"), dir.join("restored"));
+    let phrase_path = dir.join("mnemonic.txt");
+
+    for base_dir in &[&original_dir, &restored_dir] {
+        fs::create_dir(base_dir).unwrap();
+    }
+
+    let mut runner = CmdRunner::default();
+    let cmd = runner
+        .arg("init")
+        .arg("--save-mnemonic")
+        .arg(&phrase_path)
+        .arg(&original_dir)
+        .capture_stdout()
+        .run();
+
+    cmd.wait().unwrap().expect_success();
+
+    let mut runner = CmdRunner::default();
+    let cmd = runner
+        .arg("init")
+        .arg("--from-mnemonic")
+        .arg(&phrase_path)
+        .arg(&restored_dir)
+        .capture_stdout()
+        .run();
+
+    cmd.wait().unwrap().expect_success();
+
+    // The same phrase always derives the same keys
+    for keys_file in &[
+        "consensus_keypair.config.toml",
+        "network_keypairs.config.toml",
+    ] {
+        assert_eq!(
+            fs::read_to_string(original_dir.join(keys_file)).unwrap(),
+            fs::read_to_string(restored_dir.join(keys_file)).unwrap()
+        );
+    }
+}
+
+#[test]
+fn genesis_from_peer_infos() {
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+    let peers_dir = dir.join("peers");
+    fs::create_dir(&peers_dir).unwrap();
+
+    let base_dirs: Vec<_> = (0..3).map(|n| dir.join(format!("node{}", n))).collect();
+
+    for (n, base_dir) in base_dirs.iter().enumerate() {
+        fs::create_dir(base_dir).unwrap();
+        run_synchronicity_init(base_dir);
+
+        fs::copy(
+            base_dir.join("peer_info.toml"),
+            peers_dir.join(format!("node{}.toml", n)),
+        )
+        .unwrap();
+    }
+
+    let mut runner = CmdRunner::default();
+    let cmd = runner
+        .args(&["genesis", "--peers"])
+        .arg(&peers_dir)
+        .args(&base_dirs)
+        .capture_stdout()
+        .run();
+
+    cmd.wait().unwrap().expect_success();
+
+    let genesis = fs::read(base_dirs[0].join("genesis.blob")).unwrap();
+
+    for base_dir in &base_dirs {
+        // Every node knows about the whole group...
+        let node_config = NodeConfig::load_config(base_dir.join("node.config.toml"));
+        assert_eq!(node_config.consensus.consensus_peers.peers.len(), 3);
+        assert_eq!(node_config.networks[0].network_peers.peers.len(), 3);
+
+        // ...and starts from the same genesis
+        assert_eq!(fs::read(base_dir.join("genesis.blob")).unwrap(), genesis);
+
+        let validators = PeerInfoSet::load_config(base_dir.join("validators.toml"));
+        assert_eq!(validators.peers.len(), 3);
+    }
+}
+
+#[test]
+fn genesis_with_full_node() {
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+    let peers_dir = dir.join("peers");
+    fs::create_dir(&peers_dir).unwrap();
+
+    let roles = ["validator", "validator", "full-node"];
+    let base_dirs: Vec<_> = (0..roles.len())
+        .map(|n| dir.join(format!("node{}", n)))
+        .collect();
+
+    for (n, (base_dir, role)) in base_dirs.iter().zip(&roles).enumerate() {
+        fs::create_dir(base_dir).unwrap();
+
+        let mut runner = CmdRunner::default();
+        let cmd = runner
+            .args(&["init", "--role", role])
+            .arg(base_dir)
+            .capture_stdout()
+            .run();
+
+        cmd.wait().unwrap().expect_success();
+
+        fs::copy(
+            base_dir.join("peer_info.toml"),
+            peers_dir.join(format!("node{}.toml", n)),
+        )
+        .unwrap();
+    }
+
+    let mut runner = CmdRunner::default();
+    let cmd = runner
+        .args(&["genesis", "--peers"])
+        .arg(&peers_dir)
+        .args(&base_dirs)
+        .capture_stdout()
+        .run();
+
+    cmd.wait().unwrap().expect_success();
+
+    let node_config = NodeConfig::load_config(base_dirs[2].join("node.config.toml"));
+    assert_eq!(node_config.networks[0].role, "full_node");
+
+    // The full node can talk to the validators, but isn't one of them
+    assert_eq!(node_config.consensus.consensus_peers.peers.len(), 2);
+    assert_eq!(node_config.networks[0].network_peers.peers.len(), 3);
+    assert_eq!(
+        node_config.state_sync.upstream_peers.upstream_peers.len(),
+        2
+    );
+
+    // Only validators sign the ledger, so only they are trusted for proofs
+    for base_dir in &base_dirs {
+        let validators = PeerInfoSet::load_config(base_dir.join("validators.toml"));
+        assert_eq!(validators.peers.len(), 2);
+        assert!(validators.peers.iter().all(PeerInfo::is_validator));
+    }
+}
+
+#[test]
+fn verify_rejects_invalid_proof() {
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+    run_synchronicity_init(&dir);
+
+    let peer_info_set = PeerInfoSet {
+        peers: vec![PeerInfo::load_config(dir.join("peer_info.toml"))],
+    };
+
+    let validators_path = dir.join("validators.toml");
+    fs::write(&validators_path, toml::to_string(&peer_info_set).unwrap()).unwrap();
+
+    let proof_path = dir.join("bogus.synproof");
+    fs::write(&proof_path, b"SYNPROOF not really a proof").unwrap();
+
+    let mut runner = CmdRunner::default();
+    let cmd = runner
+        .args(&["verify", "--proof"])
+        .arg(&proof_path)
+        .arg("--validators")
+        .arg(&validators_path)
+        .args(&["--digest", &"00".repeat(32)])
+        .capture_stdout()
+        .run();
+
+    cmd.wait().unwrap().expect_code(1);
+}
+
+#[test]
+fn verify_rejects_proof_signed_by_other_validators() {
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+    run_synchronicity_init(&dir);
+
+    let peer_info_set = PeerInfoSet {
+        peers: vec![PeerInfo::load_config(dir.join("peer_info.toml"))],
+    };
+
+    let validators_path = dir.join("validators.toml");
+    fs::write(&validators_path, toml::to_string(&peer_info_set).unwrap()).unwrap();
+
+    // Well-formed, and signed by a quorum of the validator set it bundles,
+    // but that isn't the validator set we trust
+    let proof_path = dir.join("untrusted.synproof");
+    untrusted_proof_file().save(&proof_path).unwrap();
+
+    let mut runner = CmdRunner::default();
+    let mut cmd = runner
+        .args(&["verify", "--proof"])
+        .arg(&proof_path)
+        .arg("--validators")
+        .arg(&validators_path)
+        .args(&["--digest", &"00".repeat(32)])
+        .capture_stderr()
+        .run();
+
+    cmd.stderr()
+        .expect_regex("proof verification failed: invalid ledger info signatures");
+    cmd.wait().unwrap().expect_code(1);
+}
+
+#[test]
+fn verify_rejects_validators_file_without_validators() {
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+
+    let mut runner = CmdRunner::default();
+    let cmd = runner
+        .args(&["init", "--role", "full-node"])
+        .arg(&dir)
+        .capture_stdout()
+        .run();
+
+    cmd.wait().unwrap().expect_success();
+
+    // A full node never signs the ledger, so this trusts nobody
+    let peer_info_set = PeerInfoSet {
+        peers: vec![PeerInfo::load_config(dir.join("peer_info.toml"))],
+    };
+
+    let validators_path = dir.join("validators.toml");
+    fs::write(&validators_path, toml::to_string(&peer_info_set).unwrap()).unwrap();
+
+    let proof_path = dir.join("untrusted.synproof");
+    untrusted_proof_file().save(&proof_path).unwrap();
+
+    let mut runner = CmdRunner::default();
+    let mut cmd = runner
+        .args(&["verify", "--proof"])
+        .arg(&proof_path)
+        .arg("--validators")
+        .arg(&validators_path)
+        .args(&["--digest", &"00".repeat(32)])
+        .capture_stderr()
+        .run();
+
+    cmd.stderr().expect_regex("no validators in validator set");
+    cmd.wait().unwrap().expect_code(1);
+}
+
+/// Proof file for the reproducibility log which is signed by a validator
+/// generated for the occasion. The rest of the proof is placeholders, since
+/// verification has to fail at the signatures before it gets to them.
+fn untrusted_proof_file() -> ProofFile {
+    let private_key = Ed25519PrivateKey::try_from(&[7; 32][..]).unwrap();
+    let public_key = Ed25519PublicKey::from(&private_key);
+    let validator = AccountAddress::from_public_key(&public_key);
+
+    let mut merkle_log = MerkleLog::new();
+    merkle_log.append(log::leaf_hash(b"verdict"));
+
+    let ledger_info = LedgerInfo::new(
+        1,
+        HashValue::zero(),
+        HashValue::zero(),
+        HashValue::zero(),
+        0,
+        0,
+        None,
+    );
+
+    let mut signatures = BTreeMap::new();
+    signatures.insert(validator, private_key.sign_message(&ledger_info.hash()));
+
+    let account_state_proof = AccountStateProof::new(
+        AccumulatorProof::new(vec![]),
+        TransactionInfo::new(
+            HashValue::zero(),
+            HashValue::zero(),
+            HashValue::zero(),
+            0,
+            StatusCode::EXECUTED,
+        ),
+        SparseMerkleProof::new(None, vec![]),
+    );
+
+    let mut validator_set = ValidatorSet::default();
+    validator_set.validators.insert(validator, public_key);
+
+    ProofFile {
+        address: state::log_account(),
+        log_path: state::LOG_PATH.to_vec(),
+        leaf: b"verdict".to_vec(),
+        proof: LedgerProof {
+            inclusion: merkle_log.inclusion_proof(0, 1).unwrap(),
+            account_state: AccountStateWithProof::new(1, None, account_state_proof),
+            ledger_info: LedgerInfoWithSignatures::new(ledger_info, signatures),
+        },
+        validator_set,
+    }
+}
+
 /// Run `synchronicity init`
 fn run_synchronicity_init(output_dir: &Path) {
     let mut runner = CmdRunner::default();
@@ -0,0 +1,36 @@
+//! Client tests: sequence numbers handed out to submitted transactions
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+use std::time::Duration;
+use synchronicity::client::{SequenceTracker, TRANSACTION_EXPIRATION};
+
+/// Current time in each test (since the UNIX epoch)
+const NOW: Duration = Duration::from_secs(1_000_000);
+
+#[test]
+fn starts_from_ledger_sequence_number() {
+    assert_eq!(SequenceTracker::default().next(5, NOW), 5);
+}
+
+#[test]
+fn unexecuted_submissions_not_reused() {
+    let mut tracker = SequenceTracker::default();
+    tracker.submitted(5, NOW + TRANSACTION_EXPIRATION);
+    assert_eq!(tracker.next(5, NOW), 6);
+
+    tracker.submitted(6, NOW + TRANSACTION_EXPIRATION);
+    assert_eq!(tracker.next(5, NOW), 7);
+
+    // Transactions submitted elsewhere may have executed since
+    assert_eq!(tracker.next(9, NOW), 9);
+}
+
+#[test]
+fn expired_submissions_forgotten() {
+    let mut tracker = SequenceTracker::default();
+    tracker.submitted(5, NOW + TRANSACTION_EXPIRATION);
+
+    assert_eq!(tracker.next(5, NOW + TRANSACTION_EXPIRATION), 5);
+}
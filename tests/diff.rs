@@ -0,0 +1,142 @@
+//! Divergence diagnosis tests: comparing manifests, and comparing artifacts
+//! via the artifact service
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+use std::{convert::TryFrom, fs, path::Path};
+use synchro::crypto::ed25519::{Ed25519PrivateKey, Ed25519PublicKey};
+use synchronicity::{
+    artifact_service::{ArtifactClient, ArtifactServer},
+    diff::{BlockDigests, FileChange, ManifestDiff, BLOCK_SIZE},
+    manifest::Manifest,
+    state::BuildId,
+    worker::{self, ARTIFACTS_DIR, MANIFEST_FILE},
+};
+use tempfile::tempdir;
+
+#[test]
+fn diagnose_divergence() {
+    let tmp_dir = tempdir().unwrap();
+    let build_id = BuildId([42; 32]);
+
+    // Two builders whose `libexample.rlib` differs in its second block, and
+    // where only the dissenter produced `extra.so`
+    let mut rlib = vec![0u8; 3 * BLOCK_SIZE as usize];
+    let majority_dir = tmp_dir.path().join("majority");
+    write_artifacts(&majority_dir, &build_id, &[("libexample.rlib", &rlib)]);
+
+    rlib[BLOCK_SIZE as usize + 1] = 1;
+    let dissenter_dir = tmp_dir.path().join("dissenter");
+    write_artifacts(
+        &dissenter_dir,
+        &build_id,
+        &[("libexample.rlib", &rlib), ("extra.so", b"extra")],
+    );
+
+    let majority_addr = ArtifactServer::new(&majority_dir, private_key(1))
+        .spawn("127.0.0.1:0".parse().unwrap())
+        .unwrap();
+
+    let dissenter_addr = ArtifactServer::new(&dissenter_dir, private_key(2))
+        .spawn("127.0.0.1:0".parse().unwrap())
+        .unwrap();
+
+    let majority = ArtifactClient::new(majority_addr, public_key(1));
+    let dissenter = ArtifactClient::new(dissenter_addr, public_key(2));
+
+    // File-level differences
+    let diff = ManifestDiff::between(
+        &majority.manifest(&build_id).unwrap().unwrap(),
+        &dissenter.manifest(&build_id).unwrap().unwrap(),
+    );
+
+    let changes: Vec<_> = diff
+        .changes
+        .iter()
+        .map(|change| match change {
+            FileChange::Added(_) => format!("+{}", change.path()),
+            FileChange::Removed(_) => format!("-{}", change.path()),
+            FileChange::Modified { .. } => format!("~{}", change.path()),
+        })
+        .collect();
+
+    assert_eq!(changes, ["+extra.so", "~libexample.rlib"]);
+
+    // Byte-level differences
+    let binary_diff = majority
+        .block_digests(&build_id, "libexample.rlib")
+        .unwrap()
+        .unwrap()
+        .compare(
+            &dissenter
+                .block_digests(&build_id, "libexample.rlib")
+                .unwrap()
+                .unwrap(),
+        );
+
+    assert_eq!(binary_diff.regions, [BLOCK_SIZE..2 * BLOCK_SIZE]);
+
+    // Only artifacts in the manifest of a retained build are served
+    assert!(majority
+        .block_digests(&build_id, "../../../etc/passwd")
+        .unwrap()
+        .is_none());
+
+    assert!(majority.manifest(&BuildId([0; 32])).unwrap().is_none());
+}
+
+#[test]
+fn response_from_other_builder_rejected() {
+    let tmp_dir = tempdir().unwrap();
+    let build_id = BuildId([42; 32]);
+    write_artifacts(tmp_dir.path(), &build_id, &[("libexample.rlib", b"rlib")]);
+
+    let addr = ArtifactServer::new(tmp_dir.path(), private_key(1))
+        .spawn("127.0.0.1:0".parse().unwrap())
+        .unwrap();
+
+    // Whoever answers at a builder's address must hold the builder's key
+    let err = ArtifactClient::new(addr, public_key(2))
+        .manifest(&build_id)
+        .unwrap_err();
+
+    assert!(err.to_string().contains("bad signature"), "{}", err);
+}
+
+#[test]
+fn huge_block_digests_rejected() {
+    let digests = BlockDigests {
+        size: u64::max_value(),
+        blocks: vec![],
+    };
+
+    assert!(digests.validate().is_err());
+}
+
+/// Consensus key of the `n`th builder
+fn private_key(n: u8) -> Ed25519PrivateKey {
+    Ed25519PrivateKey::try_from(&[n; 32][..]).unwrap()
+}
+
+/// Consensus public key of the `n`th builder
+fn public_key(n: u8) -> Ed25519PublicKey {
+    Ed25519PublicKey::from(&private_key(n))
+}
+
+/// Write artifacts into a build directory as the worker would
+fn write_artifacts(scratch_dir: &Path, build_id: &BuildId, artifacts: &[(&str, &[u8])]) {
+    let artifacts_dir = worker::build_dir(scratch_dir, build_id).join(ARTIFACTS_DIR);
+    fs::create_dir_all(&artifacts_dir).unwrap();
+
+    for (path, contents) in artifacts {
+        fs::write(artifacts_dir.join(path), contents).unwrap();
+    }
+
+    let manifest = Manifest::from_dir(&artifacts_dir).unwrap();
+    fs::write(
+        worker::build_dir(scratch_dir, build_id).join(MANIFEST_FILE),
+        manifest.to_string(),
+    )
+    .unwrap();
+}
@@ -0,0 +1,291 @@
+//! Executor tests: run transactions through the state machine against an
+//! in-memory ledger
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+mod support;
+
+use self::support::{example_manifest, example_request, TestAccount, TestLedger};
+use std::{cell::RefCell, rc::Rc};
+use synchro::{
+    transaction::{SignedTransaction, TransactionPayload, VersionedPayload},
+    types::vm_error::StatusCode,
+    vm_runtime::VMVerifier,
+};
+use synchronicity::{
+    executor::SynchronicityExecutor,
+    participant::Ledger,
+    payload::{self, Commit, Payload, Reveal, PAYLOAD_VERSION},
+    state::{self, BuildId, Phase, DEFAULT_COMMIT_TIMEOUT_USECS},
+};
+
+/// Timestamp of the first block in each test
+const START: u64 = 1_000_000;
+
+/// Salt builders use in their commitments
+const SALT: [u8; 32] = [0x42; 32];
+
+/// Contents of the artifact builders claim to have built
+const ARTIFACT: &[u8] = b"example artifact";
+
+/// Digest of the sources builders claim to have built
+const SOURCE_DIGEST: [u8; 32] = [0x5C; 32];
+
+#[test]
+fn forged_sender_discarded() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let victim = TestAccount::new(&ledger, 1);
+    let forger = TestAccount::new(&ledger, 2);
+    let request = example_request();
+
+    // Validly signed, but with a key the sender's address isn't derived from
+    let payload = Payload::BuildRequest(request.clone());
+    let forged = forger.sign(victim.sender(), 0, payload.to_transaction_payload());
+    ledger.borrow_mut().mempool.push(forged);
+    ledger.borrow_mut().execute_block(START);
+
+    assert!(victim.build(&request.id()).unwrap().is_none());
+    assert_eq!(
+        ledger
+            .borrow()
+            .get_value::<u64>(&state::sequence_number_path(victim.sender())),
+        None
+    );
+
+    // The victim's own transactions are unaffected
+    victim
+        .submit(&Payload::BuildRequest(request.clone()))
+        .unwrap();
+    ledger.borrow_mut().execute_block(START + 1);
+    assert!(victim.build(&request.id()).unwrap().is_some());
+}
+
+#[test]
+fn admission_rejects_forged_sender() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let victim = TestAccount::new(&ledger, 1);
+    let forger = TestAccount::new(&ledger, 2);
+
+    let forged = forger.sign(victim.sender(), 0, example_payload());
+    assert_eq!(admit(&ledger, forged), Some(StatusCode::INVALID_AUTH_KEY));
+}
+
+#[test]
+fn admission_rejects_bad_signature() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let account = TestAccount::new(&ledger, 1);
+    let other = TestAccount::new(&ledger, 2);
+
+    // Signature by one key, presented with another
+    let txn = account.sign(account.sender(), 0, example_payload());
+    let signature = txn.signature();
+    let bad_txn = SignedTransaction::new(
+        txn.into_raw_transaction(),
+        other.public_key().clone(),
+        signature,
+    );
+
+    assert_eq!(admit(&ledger, bad_txn), Some(StatusCode::INVALID_SIGNATURE));
+}
+
+#[test]
+fn admission_rejects_malformed_payload() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let account = TestAccount::new(&ledger, 1);
+
+    let garbage = VersionedPayload::new(PAYLOAD_VERSION, b"garbage".to_vec());
+    let txn = account.sign(account.sender(), 0, garbage.to_transaction_payload());
+    assert_eq!(admit(&ledger, txn), Some(StatusCode::MALFORMED));
+}
+
+#[test]
+fn stale_sequence_number_rejected() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let account = TestAccount::new(&ledger, 1);
+
+    account
+        .submit(&Payload::BuildRequest(example_request()))
+        .unwrap();
+    ledger.borrow_mut().execute_block(START);
+
+    let replayed = account.sign(account.sender(), 0, example_payload());
+    assert_eq!(
+        admit(&ledger, replayed),
+        Some(StatusCode::SEQUENCE_NUMBER_TOO_OLD)
+    );
+}
+
+#[test]
+fn too_new_sequence_number_held_not_executed() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let account = TestAccount::new(&ledger, 1);
+
+    // Admitted, since the transactions before it may still arrive...
+    let txn = account.sign(account.sender(), 1, example_payload());
+    assert_eq!(admit(&ledger, txn.clone()), None);
+
+    // ...but not executed until they have
+    ledger.borrow_mut().mempool.push(txn);
+    ledger.borrow_mut().execute_block(START);
+
+    assert!(account.build(&example_request().id()).unwrap().is_none());
+    assert_eq!(
+        ledger
+            .borrow()
+            .get_value::<u64>(&state::sequence_number_path(account.sender())),
+        None
+    );
+}
+
+#[test]
+fn commit_after_deadline_rejected() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let (early, late) = (TestAccount::new(&ledger, 1), TestAccount::new(&ledger, 2));
+    let build_id = request_build(&ledger, &early);
+
+    early.submit(&commit(&early, build_id, SALT)).unwrap();
+    ledger.borrow_mut().execute_block(START + 1);
+
+    let deadline = START + DEFAULT_COMMIT_TIMEOUT_USECS;
+    late.submit(&commit(&late, build_id, SALT)).unwrap();
+    ledger.borrow_mut().execute_block(deadline);
+
+    let build = early.build(&build_id).unwrap().unwrap();
+    assert_eq!(build.commits.len(), 1);
+    assert!(build.commits.contains_key(&early.sender()));
+}
+
+#[test]
+fn reveal_before_reveal_phase_rejected() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let builder = TestAccount::new(&ledger, 1);
+    let build_id = request_build(&ledger, &builder);
+
+    builder.submit(&commit(&builder, build_id, SALT)).unwrap();
+    ledger.borrow_mut().execute_block(START + 1);
+
+    builder.submit(&reveal(build_id, SALT)).unwrap();
+    ledger.borrow_mut().execute_block(START + 2);
+
+    let build = builder.build(&build_id).unwrap().unwrap();
+    assert_eq!(build.phase, Phase::Committing);
+    assert!(build.reveals.is_empty());
+}
+
+#[test]
+fn commitment_mismatch_rejected() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let builder = TestAccount::new(&ledger, 1);
+    let build_id = request_build(&ledger, &builder);
+
+    builder.submit(&commit(&builder, build_id, SALT)).unwrap();
+    ledger.borrow_mut().execute_block(START + 1);
+    ledger
+        .borrow_mut()
+        .execute_block(START + DEFAULT_COMMIT_TIMEOUT_USECS);
+
+    // Opening the commitment with a different salt doesn't match
+    builder.submit(&reveal(build_id, [0xFF; 32])).unwrap();
+    ledger
+        .borrow_mut()
+        .execute_block(START + DEFAULT_COMMIT_TIMEOUT_USECS + 1);
+
+    let build = builder.build(&build_id).unwrap().unwrap();
+    assert!(build.is_revealing(START + DEFAULT_COMMIT_TIMEOUT_USECS + 1));
+    assert!(build.reveals.is_empty());
+}
+
+#[test]
+fn build_without_commits_expires() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let requester = TestAccount::new(&ledger, 1);
+    let build_id = request_build(&ledger, &requester);
+
+    ledger
+        .borrow_mut()
+        .execute_block(START + DEFAULT_COMMIT_TIMEOUT_USECS - 1);
+    assert_eq!(
+        requester.build(&build_id).unwrap().unwrap().phase,
+        Phase::Open
+    );
+
+    ledger
+        .borrow_mut()
+        .execute_block(START + DEFAULT_COMMIT_TIMEOUT_USECS);
+    assert_eq!(
+        requester.build(&build_id).unwrap().unwrap().phase,
+        Phase::Expired
+    );
+    assert!(requester.pending_builds().unwrap().is_empty());
+}
+
+#[test]
+fn build_kept_under_its_own_account() {
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let requester = TestAccount::new(&ledger, 1);
+    let build_id = request_build(&ledger, &requester);
+
+    // Only the small shared state lives under the state account
+    let build_path = state::build_path(&build_id);
+    assert_ne!(build_path.address, state::state_account());
+    assert_ne!(
+        build_path.address,
+        state::build_path(&BuildId([0; 32])).address
+    );
+
+    for path in ledger.borrow().state.keys() {
+        if path.address == build_path.address {
+            assert_eq!(path, &build_path);
+        }
+    }
+}
+
+/// Request the example build in a block at `START`
+fn request_build(ledger: &Rc<RefCell<TestLedger>>, requester: &TestAccount) -> BuildId {
+    let request = example_request();
+    requester
+        .submit(&Payload::BuildRequest(request.clone()))
+        .unwrap();
+    ledger.borrow_mut().execute_block(START);
+    request.id()
+}
+
+/// Commitment by the given builder to `ARTIFACT`
+fn commit(builder: &TestAccount, build_id: BuildId, salt: [u8; 32]) -> Payload {
+    let artifact_root = example_manifest(ARTIFACT).root();
+
+    Payload::Commit(Commit {
+        build_id,
+        source_digest: SOURCE_DIGEST,
+        commitment: payload::commitment(
+            &build_id,
+            &builder.sender(),
+            &SOURCE_DIGEST,
+            &artifact_root,
+            &salt,
+        ),
+    })
+}
+
+/// Reveal of `ARTIFACT`
+fn reveal(build_id: BuildId, salt: [u8; 32]) -> Payload {
+    Payload::Reveal(Reveal {
+        build_id,
+        manifest: example_manifest(ARTIFACT),
+        salt,
+    })
+}
+
+/// Check whether a transaction would be admitted to the mempool, returning
+/// the status code it's rejected with (if any)
+fn admit(ledger: &Rc<RefCell<TestLedger>>, txn: SignedTransaction) -> Option<StatusCode> {
+    SynchronicityExecutor::new()
+        .validate_transaction(txn, &*ledger.borrow())
+        .map(|status| status.major_status)
+}
+
+/// Transaction payload requesting a build of the example crate
+fn example_payload() -> TransactionPayload {
+    Payload::BuildRequest(example_request()).to_transaction_payload()
+}
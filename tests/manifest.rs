@@ -0,0 +1,114 @@
+//! Manifest tests: the encoding and root of a fixed set of artifacts must
+//! never change, since third parties recompute them independently
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+use sha2::{Digest, Sha256, Sha512};
+use std::fs;
+use synchronicity::manifest::{Manifest, ManifestEntry, Sha512Digest, EXECUTABLE_MODE, FILE_MODE};
+use tempfile::tempdir;
+
+/// Artifacts of the golden manifest: path, mode, and contents
+const ARTIFACTS: &[(&str, u32, &[u8])] = &[
+    ("bin/example", EXECUTABLE_MODE, b"#!/bin/sh\necho example\n"),
+    ("example-0.1.0.crate", FILE_MODE, b"example crate"),
+    ("libexample.rlib", FILE_MODE, b"example rlib"),
+];
+
+/// Encoding of the golden manifest
+const GOLDEN_ENCODING: &str = "\
+97b29a636d7ddf7bf3567ae4d48c0f2a9b03943fd808b6efbf74f1dd3db131b7 \
+073b1a261b3f2f0552a9d0267a50aa54445347eaf05085927fdb0da40df01fe8a9d36979dd56d0a9968c8a5dfe2706b91b9afa6f23765d814b8c08a7b2b29060 \
+0755 23 bin/example
+b168ce66c543f57a47946329d989505b7bae6fd2a6a607ee2b2acb85ccadd501 \
+7262b7e673d83e0e95d32f600c35c46f64f4457962e1d7ed2ca06322472426b1e35d68d280ee814b6b72b099d6ef96605c6bb5e4775032076970cbd354d4c607 \
+0644 13 example-0.1.0.crate
+78e6d7ec7bc9bfeacd32be0728ac4df03921c93a436696e6152887de057b8d84 \
+690b1484fbe71052670cd74f53efda365bb00c42cfb7c02b3136ae5c123b7a17124bd80f3a854e1a2bd0b59907229f4b3c0efd7c0d5d8e1bcfb449e45872d5cf \
+0644 12 libexample.rlib
+";
+
+/// Root of the golden manifest, i.e. `sha256sum` of its encoding
+const GOLDEN_ROOT: &str = "3365a30ecf887671c4eb7cc63d92a8ff70aad405f06130af6e15dc693a94481d";
+
+#[test]
+fn golden_encoding() {
+    let manifest = golden_manifest();
+    manifest.validate().unwrap();
+
+    assert_eq!(manifest.to_string(), GOLDEN_ENCODING);
+    assert_eq!(hex::encode(manifest.root()), GOLDEN_ROOT);
+}
+
+#[cfg(unix)]
+#[test]
+fn golden_from_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path();
+
+    for (path, mode, contents) in ARTIFACTS {
+        let file_path = dir.join(path);
+        fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        fs::write(&file_path, contents).unwrap();
+
+        // Permission bits other than the executable ones are ignored
+        let mode = if *mode == EXECUTABLE_MODE {
+            0o750
+        } else {
+            0o600
+        };
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    let manifest = Manifest::from_dir(dir).unwrap();
+    assert_eq!(manifest, golden_manifest());
+    assert_eq!(hex::encode(manifest.root()), GOLDEN_ROOT);
+}
+
+#[test]
+fn golden_decoding() {
+    let manifest: Manifest = GOLDEN_ENCODING.parse().unwrap();
+    assert_eq!(manifest, golden_manifest());
+
+    // Only the canonical encoding is accepted
+    for encoding in &[
+        GOLDEN_ENCODING.to_uppercase(),
+        GOLDEN_ENCODING.replace(" 0644 ", " 644 "),
+        GOLDEN_ENCODING.trim_end().to_owned(),
+        format!("{}\n", GOLDEN_ENCODING),
+    ] {
+        assert!(encoding.parse::<Manifest>().is_err(), "{:?}", encoding);
+    }
+}
+
+#[test]
+fn unsorted_manifest_rejected() {
+    let mut manifest = golden_manifest();
+    manifest.entries.swap(0, 1);
+    assert!(manifest.validate().is_err());
+}
+
+/// Manifest of `ARTIFACTS`
+fn golden_manifest() -> Manifest {
+    let entries = ARTIFACTS
+        .iter()
+        .map(|(path, mode, contents)| {
+            let mut entry = ManifestEntry {
+                path: (*path).to_owned(),
+                size: contents.len() as u64,
+                mode: *mode,
+                sha256: [0; 32],
+                sha512: Sha512Digest([0; 64]),
+            };
+
+            entry.sha256.copy_from_slice(&Sha256::digest(contents));
+            entry.sha512.0.copy_from_slice(&Sha512::digest(contents));
+            entry
+        })
+        .collect();
+
+    Manifest { entries }
+}
@@ -0,0 +1,54 @@
+//! Payload encoding tests
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+mod support;
+
+use self::support::example_request;
+use synchronicity::payload::{Payload, PAYLOAD_VERSION};
+
+#[test]
+fn round_trip() {
+    let payload = Payload::BuildRequest(example_request());
+    let bytes = payload.to_bytes();
+
+    assert_eq!(bytes[0], PAYLOAD_VERSION);
+    assert_eq!(Payload::from_bytes(&bytes).unwrap(), payload);
+}
+
+#[test]
+fn trailing_bytes_rejected() {
+    let mut bytes = Payload::BuildRequest(example_request()).to_bytes();
+    bytes.push(0);
+
+    assert!(Payload::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn unknown_version_rejected() {
+    let mut bytes = Payload::BuildRequest(example_request()).to_bytes();
+    bytes[0] = PAYLOAD_VERSION + 1;
+
+    assert!(Payload::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn plain_http_registry_rejected() {
+    let mut request = example_request();
+
+    request.registry = Some("sparse+https://index.example.com/".to_owned());
+    request.validate().unwrap();
+
+    request.registry = Some("sparse+http://index.example.com/".to_owned());
+    assert!(request.validate().is_err());
+}
+
+#[test]
+fn local_registry_rejected() {
+    let mut request = example_request();
+
+    // Each builder would resolve the path against its own filesystem
+    request.registry = Some("file:///var/lib/registry".to_owned());
+    assert!(request.validate().is_err());
+}
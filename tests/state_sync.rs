@@ -0,0 +1,213 @@
+//! State sync test: runs a group of nodes as subprocesses, and checks a node
+//! which joins after the group has made progress catches up with it
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use synchro::config::{NodeConfig, PeerInfo, PersistableConfig};
+use synchronicity::{
+    client::Client,
+    config::SynchronicityConfig,
+    payload::{BuildEnv, BuildRequest, Payload, Profile, Toolchain},
+};
+use tempfile::tempdir;
+
+/// Number of nodes in the group (tolerates one being offline)
+const GROUP_SIZE: usize = 4;
+
+/// First port nodes are assigned (each node uses `PORTS_PER_NODE` ports)
+const BASE_PORT: u16 = 26180;
+
+/// Number of ports each node uses
+const PORTS_PER_NODE: u16 = 3;
+
+/// How long to wait for the ledger to reach an expected state
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Ignored by default: it takes minutes and binds fixed ports starting at
+/// `BASE_PORT`, so it can't run alongside other tests using them. Run it with:
+///
+/// ```text
+/// cargo test --test state_sync -- --ignored
+/// ```
+#[test]
+#[ignore]
+fn late_node_catches_up() {
+    let tmp_dir = tempdir().unwrap();
+    let dir = tmp_dir.path().canonicalize().unwrap();
+    let base_dirs = configure_group(&dir);
+
+    // Every node but the last one starts right away
+    let mut nodes = Nodes::default();
+
+    for base_dir in &base_dirs[..GROUP_SIZE - 1] {
+        nodes.start(base_dir);
+    }
+
+    let client = Client::new(&load_config(&base_dirs[0])).unwrap();
+    let request = example_request();
+
+    wait_for("the group to accept transactions", || {
+        client
+            .submit(&Payload::BuildRequest(request.clone()))
+            .is_ok()
+    });
+
+    wait_for("the build request to be committed", || {
+        client
+            .build(&request.id())
+            .map_or(false, |build| build.is_some())
+    });
+
+    // The last node wasn't around when the request was committed, so it can
+    // only learn about it from its peers
+    let late_base_dir = &base_dirs[GROUP_SIZE - 1];
+    nodes.start(late_base_dir);
+
+    let late_client = Client::new(&load_config(late_base_dir)).unwrap();
+
+    wait_for("the late node to sync the build request", || {
+        late_client
+            .build(&request.id())
+            .map_or(false, |build| build.is_some())
+    });
+}
+
+/// Running node processes, which are killed when dropped
+#[derive(Default)]
+struct Nodes(Vec<Child>);
+
+impl Nodes {
+    /// Run `synchronicity start` in the given base directory
+    fn start(&mut self, base_dir: &Path) {
+        let child = Command::new(synchronicity_exe())
+            .arg("start")
+            .current_dir(base_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        self.0.push(child);
+    }
+}
+
+impl Drop for Nodes {
+    fn drop(&mut self) {
+        for child in &mut self.0 {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Initialize `GROUP_SIZE` nodes listening on localhost with distinct ports,
+/// and form a group from them with `synchronicity genesis`
+fn configure_group(dir: &Path) -> Vec<PathBuf> {
+    let peers_dir = dir.join("peers");
+    fs::create_dir(&peers_dir).unwrap();
+
+    let mut base_dirs = vec![];
+
+    for n in 0..GROUP_SIZE {
+        let base_dir = dir.join(format!("node{}", n));
+        fs::create_dir(&base_dir).unwrap();
+        run_synchronicity(&base_dir, &["init", "--listen", "127.0.0.1"], &[&base_dir]);
+
+        let port = BASE_PORT + n as u16 * PORTS_PER_NODE;
+        let listen_addr = format!("/ip4/127.0.0.1/tcp/{}", port);
+
+        let node_config_path = base_dir.join("node.config.toml");
+        let mut node_config = NodeConfig::load_config(&node_config_path);
+        node_config.networks[0].listen_address = listen_addr.parse().unwrap();
+        node_config.networks[0].advertised_address = listen_addr.parse().unwrap();
+        node_config.storage.port = port + 1;
+        node_config.mempool.mempool_service_port = port + 2;
+        node_config.save_config(&node_config_path);
+
+        let mut peer_info = PeerInfo::load_config(base_dir.join("peer_info.toml"));
+        peer_info.seed_ip = Some(listen_addr);
+        peer_info.save_config(peers_dir.join(format!("node{}.toml", n)));
+
+        base_dirs.push(base_dir);
+    }
+
+    let mut args = vec![peers_dir.as_path()];
+    args.extend(base_dirs.iter().map(PathBuf::as_path));
+    run_synchronicity(dir, &["genesis", "--peers"], &args);
+
+    base_dirs
+}
+
+/// Run a `synchronicity` subcommand to completion
+fn run_synchronicity(dir: &Path, args: &[&str], paths: &[&Path]) {
+    let status = Command::new(synchronicity_exe())
+        .args(args)
+        .args(paths)
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+
+    assert!(status.success(), "synchronicity {:?} failed", args);
+}
+
+/// Path to the `synchronicity` executable
+fn synchronicity_exe() -> PathBuf {
+    // Integration tests live in `target/<profile>/deps`
+    let mut path = env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(format!("synchronicity{}", env::consts::EXE_SUFFIX));
+    path
+}
+
+/// Load the `synchronicity.toml` in a node's base directory
+fn load_config(base_dir: &Path) -> SynchronicityConfig {
+    SynchronicityConfig::load_config(base_dir.join("synchronicity.toml"))
+}
+
+/// Poll until the given condition holds, panicking after `TIMEOUT`
+fn wait_for(description: &str, mut condition: impl FnMut() -> bool) {
+    let started_at = Instant::now();
+
+    while !condition() {
+        if started_at.elapsed() > TIMEOUT {
+            panic!("timed out waiting for {}", description);
+        }
+
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+/// Build request to submit (whether it's ever built doesn't matter here)
+fn example_request() -> BuildRequest {
+    BuildRequest {
+        crate_name: "example".to_owned(),
+        version: "0.1.0".to_owned(),
+        registry: None,
+        env: BuildEnv {
+            toolchain: Toolchain {
+                channel: "stable".to_owned(),
+                date: Some("2019-11-07".to_owned()),
+                commit_hash: None,
+            },
+            target: "x86_64-unknown-linux-gnu".to_owned(),
+            features: BTreeSet::new(),
+            no_default_features: false,
+            profile: Profile::Release,
+            source_date_epoch: 0,
+            remap_path_prefix: Some("/build".to_owned()),
+            rustflags: vec![],
+            lockfile: None,
+        },
+    }
+}
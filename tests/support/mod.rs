@@ -0,0 +1,288 @@
+//! Test support: an in-memory ledger which executes blocks with the
+//! Synchronicity executor, and accounts which submit transactions to it
+
+#![allow(dead_code)]
+
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256, Sha512};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    rc::Rc,
+    time::Duration,
+};
+use synchro::{
+    config::VMConfig,
+    crypto::{
+        ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+        hash::HashValue,
+    },
+    error::Result,
+    state_view::StateView,
+    transaction::{SignedTransaction, Transaction},
+    types::{
+        access_path::AccessPath,
+        account_address::AccountAddress,
+        block_metadata::BlockMetadata,
+        transaction::{RawTransaction, TransactionPayload, TransactionStatus},
+        write_set::WriteOp,
+    },
+    vm_runtime::VMExecutor,
+};
+use synchronicity::{
+    client::SequenceTracker,
+    error::{Error, ErrorKind},
+    executor::SynchronicityExecutor,
+    manifest::{Manifest, ManifestEntry, Sha512Digest, FILE_MODE},
+    participant::Ledger,
+    payload::{BuildEnv, BuildRequest, Payload, Profile, Toolchain},
+    state::{self, Build, BuildId, Params},
+};
+
+/// In-memory ledger which executes blocks with the Synchronicity executor
+#[derive(Default)]
+pub struct TestLedger {
+    /// Ledger state
+    pub state: BTreeMap<AccessPath, Vec<u8>>,
+
+    /// Transactions waiting to be included in a block
+    pub mempool: Vec<SignedTransaction>,
+}
+
+impl TestLedger {
+    /// Get and deserialize a value from the state
+    pub fn get_value<T: DeserializeOwned>(&self, path: &AccessPath) -> Option<T> {
+        self.state
+            .get(path)
+            .map(|bytes| bincode::deserialize(bytes).unwrap())
+    }
+
+    /// Add a transaction to the mempool. Like the real mempool, one which
+    /// reuses the sequence number of a queued transaction is rejected.
+    pub fn add_transaction(&mut self, txn: SignedTransaction) -> Result<(), Error> {
+        let duplicate = self.mempool.iter().any(|queued| {
+            queued.sender() == txn.sender() && queued.sequence_number() == txn.sequence_number()
+        });
+
+        if duplicate {
+            return Err(ErrorKind::Client
+                .context(format!(
+                    "mempool already holds sequence number {} from {}",
+                    txn.sequence_number(),
+                    txn.sender()
+                ))
+                .into());
+        }
+
+        self.mempool.push(txn);
+        Ok(())
+    }
+
+    /// Write the group's protocol parameters, as genesis would
+    pub fn set_params(&mut self, params: &Params) {
+        self.state
+            .insert(state::params_path(), bincode::serialize(params).unwrap());
+    }
+
+    /// Execute a block containing all transactions in the mempool
+    pub fn execute_block(&mut self, timestamp_usec: u64) {
+        let metadata = BlockMetadata::new(
+            HashValue::zero(),
+            timestamp_usec,
+            BTreeMap::new(),
+            AccountAddress::default(),
+        );
+
+        let mut transactions = vec![Transaction::BlockMetadata(metadata)];
+        transactions.extend(self.mempool.drain(..).map(Transaction::UserTransaction));
+
+        let outputs =
+            SynchronicityExecutor::execute_block(transactions, &VMConfig::default(), &*self)
+                .unwrap();
+
+        for output in outputs {
+            if let TransactionStatus::Keep(_) = output.status() {
+                for (path, op) in output.write_set() {
+                    if let WriteOp::Value(value) = op {
+                        self.state.insert(path.clone(), value.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl StateView for TestLedger {
+    fn get(&self, access_path: &AccessPath) -> Result<Option<Vec<u8>>> {
+        Ok(self.state.get(access_path).cloned())
+    }
+
+    fn multi_get(&self, access_paths: &[AccessPath]) -> Result<Vec<Option<Vec<u8>>>> {
+        access_paths.iter().map(|path| self.get(path)).collect()
+    }
+
+    fn is_genesis(&self) -> bool {
+        false
+    }
+}
+
+/// Account submitting transactions to a `TestLedger`
+pub struct TestAccount {
+    /// Shared ledger
+    ledger: Rc<RefCell<TestLedger>>,
+
+    /// Account address
+    address: AccountAddress,
+
+    /// Signing key
+    private_key: Ed25519PrivateKey,
+
+    /// Public key
+    public_key: Ed25519PublicKey,
+
+    /// Latest transaction submitted from this account
+    sequence_tracker: RefCell<SequenceTracker>,
+}
+
+impl TestAccount {
+    /// Create a test account with a key derived from the given byte
+    pub fn new(ledger: &Rc<RefCell<TestLedger>>, n: u8) -> Self {
+        let private_key = Ed25519PrivateKey::try_from(&[n; 32][..]).unwrap();
+        let public_key = Ed25519PublicKey::from(&private_key);
+
+        Self {
+            ledger: Rc::clone(ledger),
+            address: AccountAddress::from_public_key(&public_key),
+            private_key,
+            public_key,
+            sequence_tracker: RefCell::new(SequenceTracker::default()),
+        }
+    }
+}
+
+impl TestAccount {
+    /// Get this account's public key
+    pub fn public_key(&self) -> &Ed25519PublicKey {
+        &self.public_key
+    }
+
+    /// Sign a transaction with this account's key, claiming to be sent from
+    /// the given account (which needn't be this one)
+    pub fn sign(
+        &self,
+        sender: AccountAddress,
+        sequence_number: u64,
+        payload: TransactionPayload,
+    ) -> SignedTransaction {
+        RawTransaction::new(
+            sender,
+            sequence_number,
+            payload,
+            1_000_000,
+            0,
+            Duration::from_secs(u64::max_value()),
+        )
+        .sign(&self.private_key, self.public_key.clone())
+        .unwrap()
+        .into_inner()
+    }
+}
+
+impl Ledger for TestAccount {
+    fn sender(&self) -> AccountAddress {
+        self.address
+    }
+
+    fn now(&self) -> Result<u64, Error> {
+        Ok(self
+            .ledger
+            .borrow()
+            .get_value(&state::timestamp_path())
+            .unwrap_or(0))
+    }
+
+    fn params(&self) -> Result<Params, Error> {
+        Ok(self
+            .ledger
+            .borrow()
+            .get_value(&state::params_path())
+            .unwrap_or_default())
+    }
+
+    fn pending_builds(&self) -> Result<BTreeSet<BuildId>, Error> {
+        Ok(self
+            .ledger
+            .borrow()
+            .get_value(&state::pending_builds_path())
+            .unwrap_or_default())
+    }
+
+    fn build(&self, build_id: &BuildId) -> Result<Option<Build>, Error> {
+        Ok(self.ledger.borrow().get_value(&state::build_path(build_id)))
+    }
+
+    fn submit(&self, payload: &Payload) -> Result<u64, Error> {
+        let mut ledger = self.ledger.borrow_mut();
+        let mut sequence_tracker = self.sequence_tracker.borrow_mut();
+
+        // Like the real client, this only knows about queued transactions it
+        // submitted itself. Test transactions never expire.
+        let committed = ledger
+            .get_value::<u64>(&state::sequence_number_path(self.address))
+            .unwrap_or(0);
+
+        let sequence_number = sequence_tracker.next(committed, Duration::default());
+
+        ledger.add_transaction(self.sign(
+            self.address,
+            sequence_number,
+            payload.to_transaction_payload(),
+        ))?;
+
+        sequence_tracker.submitted(sequence_number, Duration::from_secs(u64::max_value()));
+        Ok(sequence_number)
+    }
+}
+
+/// Build request for tests which never actually build it
+pub fn example_request() -> BuildRequest {
+    BuildRequest {
+        crate_name: "example".to_owned(),
+        version: "0.1.0".to_owned(),
+        registry: None,
+        env: BuildEnv {
+            toolchain: Toolchain {
+                channel: "stable".to_owned(),
+                date: Some("2019-11-07".to_owned()),
+                commit_hash: None,
+            },
+            target: "x86_64-unknown-linux-gnu".to_owned(),
+            features: BTreeSet::new(),
+            no_default_features: false,
+            profile: Profile::Release,
+            source_date_epoch: 0,
+            remap_path_prefix: Some("/build".to_owned()),
+            rustflags: vec![],
+            lockfile: None,
+        },
+    }
+}
+
+/// Manifest of a single artifact with the given contents
+pub fn example_manifest(contents: &[u8]) -> Manifest {
+    let mut entry = ManifestEntry {
+        path: "libexample.rlib".to_owned(),
+        size: contents.len() as u64,
+        mode: FILE_MODE,
+        sha256: [0u8; 32],
+        sha512: Sha512Digest([0u8; 64]),
+    };
+
+    entry.sha256.copy_from_slice(&Sha256::digest(contents));
+    entry.sha512.0.copy_from_slice(&Sha512::digest(contents));
+
+    Manifest {
+        entries: vec![entry],
+    }
+}
@@ -0,0 +1,209 @@
+//! Verdict tests: group the results builders revealed for a build and check
+//! them against the threshold
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+mod support;
+
+use self::support::{example_manifest, example_request};
+use std::collections::BTreeSet;
+use synchro::types::account_address::AccountAddress;
+use synchronicity::{
+    state::{Build, Digest, Params},
+    verdict::{BuildResult, Verdict, VerdictStatus},
+};
+
+/// Timestamp the example build was requested at
+const START: u64 = 1_000_000;
+
+/// Digest of the sources builders claim to have built
+const SOURCE_DIGEST: Digest = [0x5C; 32];
+
+/// Threshold for a group of 4 builders (2f+1 where f = 1)
+const THRESHOLD: usize = 3;
+
+#[test]
+fn reproduced_with_exactly_threshold() {
+    let mut build = example_build();
+    reveal(&mut build, 1, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 2, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 3, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 4, SOURCE_DIGEST, b"other artifact");
+
+    match VerdictStatus::decide(&build, THRESHOLD) {
+        VerdictStatus::Reproduced {
+            source_digest,
+            artifact_root,
+            builders,
+            dissenters,
+            ..
+        } => {
+            assert_eq!(source_digest, SOURCE_DIGEST);
+            assert_eq!(artifact_root, example_manifest(b"artifact").root());
+            assert_eq!(builders, (1..=3).map(builder).collect::<BTreeSet<_>>());
+            assert_eq!(dissenters.len(), 1);
+            assert!(dissenters.contains_key(&builder(4)));
+        }
+        status => panic!("build not reproduced: {:?}", status),
+    }
+}
+
+#[test]
+fn one_fewer_than_threshold_diverges() {
+    let mut build = example_build();
+    reveal(&mut build, 1, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 2, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 3, [0xFF; 32], b"artifact");
+    reveal(&mut build, 4, SOURCE_DIGEST, b"other artifact");
+
+    match VerdictStatus::decide(&build, THRESHOLD) {
+        VerdictStatus::Diverged {
+            source_digest,
+            plurality_root,
+            dissenters,
+        } => {
+            assert_eq!(source_digest, SOURCE_DIGEST);
+            assert_eq!(plurality_root, example_manifest(b"artifact").root());
+            assert_eq!(dissenters.len(), 2);
+
+            // Same artifacts, but built from different sources
+            assert_eq!(
+                dissenters[&builder(3)],
+                BuildResult {
+                    source_digest: [0xFF; 32],
+                    artifact_root: plurality_root,
+                }
+            );
+            assert_eq!(dissenters[&builder(4)].source_digest, SOURCE_DIGEST);
+        }
+        status => panic!("build didn't diverge: {:?}", status),
+    }
+}
+
+#[test]
+fn missing_manifest_diverges() {
+    let mut build = example_build();
+    reveal(&mut build, 1, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 2, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 3, SOURCE_DIGEST, b"artifact");
+    build.manifests.clear();
+
+    match VerdictStatus::decide(&build, THRESHOLD) {
+        VerdictStatus::Diverged {
+            plurality_root,
+            dissenters,
+            ..
+        } => {
+            assert_eq!(plurality_root, example_manifest(b"artifact").root());
+            assert!(dissenters.is_empty());
+        }
+        status => panic!("build didn't diverge: {:?}", status),
+    }
+}
+
+#[test]
+fn tie_broken_by_smallest_result() {
+    let (first, second) = (&b"artifact"[..], &b"other artifact"[..]);
+    let smallest_root = example_manifest(first)
+        .root()
+        .min(example_manifest(second).root());
+
+    // Which builders revealed which root doesn't matter
+    for (a, b) in &[(first, second), (second, first)] {
+        let mut build = example_build();
+        reveal(&mut build, 1, SOURCE_DIGEST, a);
+        reveal(&mut build, 2, SOURCE_DIGEST, a);
+        reveal(&mut build, 3, SOURCE_DIGEST, b);
+        reveal(&mut build, 4, SOURCE_DIGEST, b);
+
+        match VerdictStatus::decide(&build, THRESHOLD) {
+            VerdictStatus::Diverged {
+                plurality_root,
+                dissenters,
+                ..
+            } => {
+                assert_eq!(plurality_root, smallest_root);
+                assert_eq!(dissenters.len(), 2);
+            }
+            status => panic!("build didn't diverge: {:?}", status),
+        }
+    }
+}
+
+#[test]
+fn too_few_reveals_insufficient() {
+    let mut build = example_build();
+    reveal(&mut build, 1, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 2, SOURCE_DIGEST, b"artifact");
+
+    assert_eq!(
+        VerdictStatus::decide(&build, THRESHOLD),
+        VerdictStatus::InsufficientParticipation { revealed: 2 }
+    );
+}
+
+#[test]
+fn reveals_without_sources_not_counted() {
+    let mut build = example_build();
+    reveal(&mut build, 1, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 2, SOURCE_DIGEST, b"artifact");
+    reveal(&mut build, 3, SOURCE_DIGEST, b"artifact");
+    build.sources.remove(&builder(3));
+
+    assert_eq!(
+        VerdictStatus::decide(&build, THRESHOLD),
+        VerdictStatus::InsufficientParticipation { revealed: 2 }
+    );
+}
+
+#[test]
+fn open_builder_set_never_reproduces() {
+    let params = Params::default();
+    assert_eq!(params.threshold(), None);
+
+    // A single account committing and revealing can't reproduce a build
+    let mut build = example_build();
+    reveal(&mut build, 1, SOURCE_DIGEST, b"artifact");
+
+    let verdict = Verdict::decide(example_request().id(), &build, START, &params, 0);
+    assert_eq!(verdict.threshold, None);
+    assert_eq!(
+        verdict.status,
+        VerdictStatus::InsufficientParticipation { revealed: 1 }
+    );
+}
+
+#[test]
+fn threshold_is_two_thirds_plus_one() {
+    let params = |n: u8| Params {
+        builders: (1..=n).map(builder).collect(),
+        ..Params::default()
+    };
+
+    assert_eq!(params(1).threshold(), Some(1));
+    assert_eq!(params(3).threshold(), Some(3));
+    assert_eq!(params(4).threshold(), Some(THRESHOLD));
+    assert_eq!(params(7).threshold(), Some(5));
+}
+
+/// Build record for the example request
+fn example_build() -> Build {
+    Build::new(example_request(), builder(0), START, &Params::default())
+}
+
+/// Address of the `n`th builder
+fn builder(n: u8) -> AccountAddress {
+    AccountAddress::new([n; 32])
+}
+
+/// Record a reveal by the `n`th builder of an artifact with the given
+/// contents, built from the given sources
+fn reveal(build: &mut Build, n: u8, source_digest: Digest, contents: &[u8]) {
+    let manifest = example_manifest(contents);
+    let artifact_root = manifest.root();
+
+    build.sources.insert(builder(n), source_digest);
+    build.reveals.insert(builder(n), artifact_root);
+    build.manifests.insert(artifact_root, manifest);
+}
@@ -0,0 +1,178 @@
+//! crates.io index watcher tests, using a plain directory as the index
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+mod support;
+
+use self::support::{TestAccount, TestLedger};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+use synchronicity::{
+    participant::Ledger,
+    payload::{BuildEnv, BuildRequest, Profile, Toolchain},
+    registry,
+    watcher::{CrateFilter, Index, IndexWatcher},
+};
+use tempfile::tempdir;
+
+#[test]
+fn submits_new_matching_versions_once() {
+    let tmp_dir = tempdir().unwrap();
+    let index_dir = tmp_dir.path().join("index");
+    publish(&index_dir, "serde", "1.0.0", false);
+    publish(&index_dir, "rand", "0.7.0", false);
+
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+
+    let mut watchers: Vec<_> = (1..3)
+        .map(|n| {
+            let scratch_dir = tmp_dir.path().join(format!("scratch{}", n));
+            fs::create_dir_all(&scratch_dir).unwrap();
+
+            IndexWatcher::new(
+                TestAccount::new(&ledger, n),
+                Index::open(&index_dir).unwrap(),
+                CrateFilter::new(vec!["serd?", "tokio-*"]),
+                build_env(),
+                scratch_dir,
+            )
+            .with_stagger(Duration::from_secs(0))
+        })
+        .collect();
+
+    // Versions published before the watcher first ran aren't built
+    for watcher in &mut watchers {
+        watcher.step().unwrap();
+    }
+
+    assert!(ledger.borrow().mempool.is_empty());
+
+    publish(&index_dir, "serde", "1.0.1", false);
+    publish(&index_dir, "serde", "1.0.2", true);
+    publish(&index_dir, "rand", "0.7.1", false);
+
+    // The first watcher submits the only new, matching, unyanked version...
+    watchers[0].step().unwrap();
+    assert_eq!(ledger.borrow().mempool.len(), 1);
+    ledger.borrow_mut().execute_block(1_000_000);
+
+    let request = BuildRequest {
+        crate_name: "serde".to_owned(),
+        version: "1.0.1".to_owned(),
+        registry: None,
+        env: build_env(),
+    };
+
+    assert!(watchers[0].ledger().build(&request.id()).unwrap().is_some());
+
+    // ...and the second sees it has already been requested
+    watchers[1].step().unwrap();
+    assert!(ledger.borrow().mempool.is_empty());
+
+    // Nothing is resubmitted later
+    watchers[0].step().unwrap();
+    assert!(ledger.borrow().mempool.is_empty());
+}
+
+#[test]
+fn submits_every_due_version_in_one_scan() {
+    let tmp_dir = tempdir().unwrap();
+    let index_dir = tmp_dir.path().join("index");
+    publish(&index_dir, "serde", "1.0.0", false);
+
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let mut watcher = IndexWatcher::new(
+        TestAccount::new(&ledger, 1),
+        Index::open(&index_dir).unwrap(),
+        CrateFilter::new(vec!["serde"]),
+        build_env(),
+        tmp_dir.path(),
+    )
+    .with_stagger(Duration::from_secs(0));
+
+    watcher.step().unwrap();
+
+    let versions = ["1.0.1", "1.0.2", "1.0.3"];
+
+    for version in &versions {
+        publish(&index_dir, "serde", version, false);
+    }
+
+    // Each request gets its own sequence number, so all of them execute
+    watcher.step().unwrap();
+    assert_eq!(ledger.borrow().mempool.len(), versions.len());
+    ledger.borrow_mut().execute_block(1_000_000);
+
+    for version in &versions {
+        let request = BuildRequest {
+            crate_name: "serde".to_owned(),
+            version: (*version).to_owned(),
+            registry: None,
+            env: build_env(),
+        };
+
+        assert!(watcher.ledger().build(&request.id()).unwrap().is_some());
+    }
+}
+
+#[test]
+fn crate_filter() {
+    let filter = CrateFilter::new(vec!["serde", "tokio-*", "?et"]);
+
+    assert!(filter.matches("serde"));
+    assert!(filter.matches("Serde"));
+    assert!(!filter.matches("serde_json"));
+    assert!(filter.matches("tokio-util"));
+    assert!(!filter.matches("tokio"));
+    assert!(filter.matches("net"));
+    assert!(!filter.matches("et"));
+}
+
+/// Environment the watchers request builds in
+fn build_env() -> BuildEnv {
+    BuildEnv {
+        toolchain: Toolchain {
+            channel: "stable".to_owned(),
+            date: Some("2019-12-19".to_owned()),
+            commit_hash: None,
+        },
+        target: "x86_64-unknown-linux-gnu".to_owned(),
+        features: BTreeSet::new(),
+        no_default_features: false,
+        profile: Profile::Release,
+        source_date_epoch: 0,
+        remap_path_prefix: Some("/build".to_owned()),
+        rustflags: vec![],
+        lockfile: None,
+    }
+}
+
+/// Add a version of a crate to an index directory
+fn publish(index_dir: &Path, name: &str, version: &str, yanked: bool) {
+    let path = index_dir.join(registry::crate_path(name));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+
+    writeln!(
+        file,
+        r#"{{"name":"{}","vers":"{}","deps":[],"cksum":"{}","features":{{}},"yanked":{}}}"#,
+        name,
+        version,
+        "00".repeat(32),
+        yanked
+    )
+    .unwrap();
+}
@@ -0,0 +1,439 @@
+//! Build worker tests, using the local backend so neither Docker nor network
+//! access is required
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+
+mod support;
+
+use self::support::{TestAccount, TestLedger};
+use flate2::{write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fs::{self, File},
+    path::Path,
+    process::Command,
+    rc::Rc,
+};
+use synchro::types::account_address::AccountAddress;
+use synchronicity::{
+    backend::LocalBackend,
+    error::ErrorKind,
+    participant::{Ledger, Participant},
+    payload::{BuildEnv, BuildRequest, Payload, Profile, Toolchain},
+    registry,
+    state::{Params, Phase, DEFAULT_COMMIT_TIMEOUT_USECS},
+    verdict::{Verdict, VerdictStatus},
+    worker::{Worker, ARTIFACTS_DIR, MANIFEST_FILE},
+};
+use tempfile::tempdir;
+
+/// Name of the example crate
+const CRATE_NAME: &str = "example";
+
+/// Version of the example crate
+const CRATE_VERSION: &str = "0.1.0";
+
+#[test]
+fn local_build() {
+    let tmp_dir = tempdir().unwrap();
+    let crates_dir = tmp_dir.path().join("crates");
+    write_example_crate(&crates_dir);
+
+    let request = example_request();
+    let worker = Worker::new(
+        tmp_dir.path().join("scratch"),
+        Box::new(LocalBackend::new(&crates_dir)),
+    );
+
+    let output = worker.build(&request).unwrap();
+    let manifest = &output.manifest;
+
+    let paths: Vec<_> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, ["example-0.1.0.crate", "libexample.rlib"]);
+    assert_eq!(manifest.entries[1].mode, 0o644);
+
+    // The sources built are the ones listed in the index, and are covered by
+    // the manifest
+    assert_eq!(output.source_digest, example_crate_digest(&crates_dir));
+    assert_eq!(manifest.entries[0].sha256, output.source_digest);
+
+    // Artifacts are kept in the build directory
+    let artifacts_dir = worker.build_dir(&request).join(ARTIFACTS_DIR);
+    assert!(artifacts_dir.join("libexample.rlib").is_file());
+
+    // The encoded manifest is kept alongside them
+    let manifest_file = worker.build_dir(&request).join(MANIFEST_FILE);
+    assert_eq!(
+        fs::read_to_string(manifest_file).unwrap(),
+        manifest.to_string()
+    );
+
+    // Rebuilding produces an identical manifest
+    assert_eq!(worker.build(&request).unwrap().manifest, *manifest);
+}
+
+#[test]
+fn alternative_registry_build() {
+    let tmp_dir = tempdir().unwrap();
+    let mirror_dir = tmp_dir.path().join("registry");
+    write_example_crate(&mirror_dir);
+
+    let index_url = "sparse+https://registry.example.com/";
+    let request = BuildRequest {
+        registry: Some(index_url.to_owned()),
+        ..example_request()
+    };
+
+    request.validate().unwrap();
+
+    // The backend's crates.io mirror is empty, so the crate can only have
+    // come from the registry's mirror
+    let empty_dir = tmp_dir.path().join("empty");
+    fs::create_dir_all(&empty_dir).unwrap();
+
+    let worker = Worker::new(
+        tmp_dir.path().join("scratch"),
+        Box::new(LocalBackend::new(&empty_dir).with_registry_mirror(index_url, &mirror_dir)),
+    );
+
+    let manifest = worker.build(&request).unwrap().manifest;
+    let paths: Vec<_> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, ["example-0.1.0.crate", "libexample.rlib"]);
+
+    // The registry is part of the build ID
+    assert_ne!(request.id(), example_request().id());
+}
+
+#[test]
+fn unpinned_lockfile_rejected() {
+    let tmp_dir = tempdir().unwrap();
+    let crates_dir = tmp_dir.path().join("crates");
+    write_example_crate(&crates_dir);
+
+    let mut request = example_request();
+    request.env.lockfile = Some([0; 32]);
+
+    let worker = Worker::new(
+        tmp_dir.path().join("scratch"),
+        Box::new(LocalBackend::new(&crates_dir)),
+    );
+
+    // The example crate doesn't ship a `Cargo.lock` at all
+    let err = worker.build(&request).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Build);
+}
+
+#[test]
+fn tampered_crate_rejected() {
+    let tmp_dir = tempdir().unwrap();
+    let crates_dir = tmp_dir.path().join("crates");
+    write_example_crate(&crates_dir);
+
+    // Swap in a different tarball after the index was written
+    let crate_file = crates_dir.join(format!("{}-{}.crate", CRATE_NAME, CRATE_VERSION));
+    let mut tampered = fs::read(&crate_file).unwrap();
+    tampered.extend_from_slice(b"trojan");
+    fs::write(&crate_file, tampered).unwrap();
+
+    let worker = Worker::new(
+        tmp_dir.path().join("scratch"),
+        Box::new(LocalBackend::new(&crates_dir)),
+    );
+
+    let err = worker.build(&example_request()).unwrap_err();
+    assert_eq!(*err.kind(), ErrorKind::Checksum);
+}
+
+#[test]
+fn submit_build_commit_reveal_verdict() {
+    let tmp_dir = tempdir().unwrap();
+    let crates_dir = tmp_dir.path().join("crates");
+    write_example_crate(&crates_dir);
+
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let requester = TestAccount::new(&ledger, 1);
+
+    let builders: Vec<_> = (2..4).map(|n| TestAccount::new(&ledger, n)).collect();
+
+    ledger.borrow_mut().set_params(&Params {
+        builders: builders.iter().map(Ledger::sender).collect(),
+        ..Params::default()
+    });
+
+    let mut participants: Vec<_> = builders
+        .into_iter()
+        .enumerate()
+        .map(|(n, builder)| {
+            let scratch_dir = tmp_dir.path().join(format!("scratch{}", n));
+            let worker = Worker::new(scratch_dir, Box::new(LocalBackend::new(&crates_dir)));
+            Participant::new(builder, worker)
+        })
+        .collect();
+
+    // Submit
+    let request = example_request();
+    requester
+        .submit(&Payload::BuildRequest(request.clone()))
+        .unwrap();
+
+    let start = 1_000_000;
+    ledger.borrow_mut().execute_block(start);
+
+    // Build and commit
+    for participant in &mut participants {
+        participant.step().unwrap();
+    }
+
+    ledger.borrow_mut().execute_block(start + 1);
+    let build = requester.build(&request.id()).unwrap().unwrap();
+    assert_eq!(build.commits.len(), participants.len());
+
+    // Close the commit phase, then reveal
+    ledger
+        .borrow_mut()
+        .execute_block(start + DEFAULT_COMMIT_TIMEOUT_USECS);
+
+    for participant in &mut participants {
+        participant.step().unwrap();
+    }
+
+    ledger
+        .borrow_mut()
+        .execute_block(start + DEFAULT_COMMIT_TIMEOUT_USECS + 1);
+
+    // Verdict
+    let verdict = Verdict::lookup(
+        &*ledger.borrow(),
+        None,
+        &request.crate_name,
+        &request.version,
+        &request.env.target,
+    )
+    .unwrap()
+    .expect("no verdict reached");
+
+    assert_eq!(verdict.build_id, request.id());
+    assert_eq!(verdict.log_index, 0);
+
+    match &verdict.status {
+        VerdictStatus::Reproduced {
+            source_digest,
+            manifest,
+            builders,
+            dissenters,
+            ..
+        } => {
+            assert_eq!(*source_digest, example_crate_digest(&crates_dir));
+            assert_eq!(builders.len(), participants.len());
+            assert!(dissenters.is_empty());
+            assert_eq!(manifest.entries[1].path, "libexample.rlib");
+        }
+        status => panic!("build not reproduced: {:?}", status),
+    }
+
+    assert!(requester.pending_builds().unwrap().is_empty());
+}
+
+#[test]
+fn several_builds_in_one_step() {
+    let tmp_dir = tempdir().unwrap();
+    let crates_dir = tmp_dir.path().join("crates");
+    write_example_crate(&crates_dir);
+
+    let ledger = Rc::new(RefCell::new(TestLedger::default()));
+    let requester = TestAccount::new(&ledger, 1);
+    let builder = TestAccount::new(&ledger, 2);
+    let sender = builder.sender();
+
+    ledger.borrow_mut().set_params(&Params {
+        builders: vec![sender].into_iter().collect(),
+        ..Params::default()
+    });
+
+    let worker = Worker::new(
+        tmp_dir.path().join("scratch"),
+        Box::new(LocalBackend::new(&crates_dir)),
+    );
+
+    let mut participant = Participant::new(builder, worker);
+
+    let mut other_request = example_request();
+    other_request.env.source_date_epoch = 1;
+    let requests = [example_request(), other_request];
+
+    for request in &requests {
+        requester
+            .submit(&Payload::BuildRequest(request.clone()))
+            .unwrap();
+    }
+
+    let start = 1_000_000;
+    ledger.borrow_mut().execute_block(start);
+    assert_eq!(requester.pending_builds().unwrap().len(), requests.len());
+
+    // Commit to both before either commitment has executed
+    participant.step().unwrap();
+    ledger.borrow_mut().execute_block(start + 1);
+
+    for request in &requests {
+        let build = requester.build(&request.id()).unwrap().unwrap();
+        assert!(build.commits.contains_key(&sender));
+    }
+
+    // Every builder committed, so the reveal phase starts with the next
+    // block: reveal both before either reveal has executed
+    ledger.borrow_mut().execute_block(start + 2);
+    participant.step().unwrap();
+    ledger.borrow_mut().execute_block(start + 3);
+    ledger.borrow_mut().execute_block(start + 4);
+
+    for request in &requests {
+        let build = requester.build(&request.id()).unwrap().unwrap();
+        assert!(build.reveals.contains_key(&sender));
+        assert_eq!(build.phase, Phase::Finalized);
+    }
+
+    assert!(requester.pending_builds().unwrap().is_empty());
+}
+
+#[test]
+fn recommit_reuses_saved_reveal() {
+    let tmp_dir = tempdir().unwrap();
+    let crates_dir = tmp_dir.path().join("crates");
+    write_example_crate(&crates_dir);
+
+    let request = example_request();
+    let builder = AccountAddress::new([2; 32]);
+    let worker = Worker::new(
+        tmp_dir.path().join("scratch"),
+        Box::new(LocalBackend::new(&crates_dir)),
+    );
+
+    let commit = worker.commit(&request, &builder).unwrap();
+    let reveal = worker.reveal(&request).unwrap().unwrap();
+
+    // Committing again (e.g. after a restart, while the first commitment is
+    // still in mempool) must not replace the reveal which opens it
+    assert_eq!(worker.commit(&request, &builder).unwrap(), commit);
+    assert_eq!(worker.reveal(&request).unwrap().unwrap(), reveal);
+    assert_eq!(
+        commit.commitment,
+        reveal.commitment(&builder, &commit.source_digest)
+    );
+}
+
+/// Build request for the example crate
+fn example_request() -> BuildRequest {
+    BuildRequest {
+        crate_name: CRATE_NAME.to_owned(),
+        version: CRATE_VERSION.to_owned(),
+        registry: None,
+        env: BuildEnv {
+            toolchain: host_toolchain(),
+            target: rustc_field("host"),
+            features: BTreeSet::new(),
+            no_default_features: false,
+            profile: Profile::Release,
+            source_date_epoch: 0,
+            remap_path_prefix: Some("/build".to_owned()),
+            rustflags: vec![],
+            lockfile: None,
+        },
+    }
+}
+
+/// Write a `.crate` tarball for a minimal library crate with no dependencies,
+/// along with a registry index for it, into the given directory
+fn write_example_crate(crates_dir: &Path) {
+    let prefix = format!("{}-{}", CRATE_NAME, CRATE_VERSION);
+    let cargo_toml = format!(
+        "[package]\nname = \"{}\"\nversion = \"{}\"\nedition = \"2018\"\n",
+        CRATE_NAME, CRATE_VERSION
+    );
+    let lib_rs = "pub fn answer() -> u32 {\n    42\n}\n";
+
+    fs::create_dir_all(crates_dir).unwrap();
+    let file = File::create(crates_dir.join(format!("{}.crate", prefix))).unwrap();
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    for (path, contents) in &[("Cargo.toml", cargo_toml.as_str()), ("src/lib.rs", lib_rs)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        archive
+            .append_data(
+                &mut header,
+                format!("{}/{}", prefix, path),
+                contents.as_bytes(),
+            )
+            .unwrap();
+    }
+
+    archive.into_inner().unwrap().finish().unwrap();
+
+    fs::write(
+        crates_dir.join(registry::CONFIG_FILE),
+        format!(
+            r#"{{"dl":"file://{}/{{crate}}-{{version}}.crate"}}"#,
+            crates_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let index_file = crates_dir.join(registry::crate_path(CRATE_NAME));
+    fs::create_dir_all(index_file.parent().unwrap()).unwrap();
+
+    fs::write(
+        index_file,
+        format!(
+            r#"{{"name":"{}","vers":"{}","deps":[],"cksum":"{}","features":{{}},"yanked":false}}"#,
+            CRATE_NAME,
+            CRATE_VERSION,
+            hex::encode(example_crate_digest(crates_dir))
+        ),
+    )
+    .unwrap();
+}
+
+/// Compute the SHA-256 digest of the example crate's `.crate` tarball
+fn example_crate_digest(crates_dir: &Path) -> [u8; 32] {
+    let crate_file = crates_dir.join(format!("{}-{}.crate", CRATE_NAME, CRATE_VERSION));
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&Sha256::digest(&fs::read(crate_file).unwrap()));
+    digest
+}
+
+/// Describe the host's toolchain, pinned by commit hash
+fn host_toolchain() -> Toolchain {
+    let release = rustc_field("release");
+
+    let channel = match release.rsplit('-').next() {
+        Some(suffix) if suffix.starts_with("beta") => "beta",
+        Some(suffix) if suffix.starts_with("nightly") => "nightly",
+        _ => "stable",
+    };
+
+    Toolchain {
+        channel: channel.to_owned(),
+        date: None,
+        commit_hash: Some(rustc_field("commit-hash")),
+    }
+}
+
+/// Get a field of the host `rustc`'s version information
+fn rustc_field(name: &str) -> String {
+    let output = Command::new("rustc").arg("-vV").output().unwrap();
+    let prefix = format!("{}: ", name);
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .find(|line| line.starts_with(&prefix))
+        .map(|line| line[prefix.len()..].to_owned())
+        .unwrap_or_else(|| panic!("rustc -vV has no {} field", name))
+}